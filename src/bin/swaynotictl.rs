@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Write};
 use std::os::unix::net::UnixStream;
 use std::path::PathBuf;
@@ -62,12 +63,67 @@ enum Command {
         /// Action key
         action: String,
     },
+    /// Show apps that have been rate limited
+    RateLimits,
+    /// Print notification events as they happen, until interrupted
+    Subscribe,
+    /// Inspect and toggle notification rules
+    #[command(alias = "rule")]
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RulesCommand {
+    /// List configured rules and whether they are enabled
+    List,
+    /// Enable the rules with this name
+    Enable {
+        /// Rule name
+        name: String,
+    },
+    /// Disable the rules with this name until enabled again
+    Disable {
+        /// Rule name
+        name: String,
+    },
+    /// Run a synthetic notification through the rules without showing it
+    Test {
+        /// Application name
+        #[arg(long, default_value = "")]
+        app: String,
+        /// Summary text
+        #[arg(long, default_value = "")]
+        summary: String,
+        /// Body text
+        #[arg(long, default_value = "")]
+        body: String,
+        /// Urgency (low, normal, critical)
+        #[arg(long)]
+        urgency: Option<String>,
+        /// Hint as key=value (repeatable)
+        #[arg(long = "hint", value_parser = parse_hint)]
+        hints: Vec<(String, String)>,
+        /// Action key offered by the notification (repeatable)
+        #[arg(long = "action")]
+        actions: Vec<String>,
+    },
+}
+
+fn parse_hint(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("invalid hint '{}', expected key=value", s))
 }
 
 #[derive(Serialize)]
 #[serde(tag = "command", rename_all = "snake_case")]
 enum IpcCommand {
-    Dismiss { id: u32 },
+    Dismiss {
+        id: u32,
+    },
     DismissAll,
     ToggleDnd,
     EnableDnd,
@@ -81,7 +137,27 @@ enum IpcCommand {
     GetCount,
     ReloadConfig,
     GetNotifications,
-    InvokeAction { id: u32, action: String },
+    InvokeAction {
+        id: u32,
+        action: String,
+    },
+    ListRules,
+    EnableRule {
+        name: String,
+    },
+    DisableRule {
+        name: String,
+    },
+    GetRateLimits,
+    Subscribe,
+    TestRules {
+        app_name: String,
+        summary: String,
+        body: String,
+        urgency: Option<String>,
+        hints: HashMap<String, String>,
+        actions: Vec<String>,
+    },
 }
 
 #[derive(Deserialize)]
@@ -116,10 +192,65 @@ fn send_command(socket_path: &PathBuf, command: IpcCommand) -> Result<IpcRespons
     Ok(response)
 }
 
+/// Print events streamed by the daemon until it disconnects
+fn subscribe(socket_path: &PathBuf, json: bool) -> Result<()> {
+    let mut stream = UnixStream::connect(socket_path)
+        .with_context(|| format!("Failed to connect to socket: {:?}", socket_path))?;
+
+    let command = serde_json::to_string(&IpcCommand::Subscribe)? + "\n";
+    stream.write_all(command.as_bytes())?;
+    stream.flush()?;
+
+    let mut lines = BufReader::new(stream).lines();
+
+    // The first line acknowledges the subscription
+    let response: IpcResponse = match lines.next() {
+        Some(line) => serde_json::from_str(&line?).context("Failed to parse response")?,
+        None => anyhow::bail!("Daemon closed the connection"),
+    };
+    if !response.success {
+        anyhow::bail!(
+            "{}",
+            response.error.unwrap_or_else(|| "Unknown error".into())
+        );
+    }
+
+    for line in lines {
+        let line = line?;
+        if json {
+            println!("{}", line);
+        } else {
+            match serde_json::from_str::<serde_json::Value>(&line) {
+                Ok(event) => print_event(&event),
+                Err(_) => println!("{}", line),
+            }
+        }
+        std::io::stdout().flush()?;
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let args = Args::parse();
     let socket_path = get_socket_path(args.socket);
 
+    if let Command::Subscribe = args.command {
+        return subscribe(&socket_path, args.json);
+    }
+
+    // Rule and rate limit commands return structured data with their own layout
+    let printer: Option<fn(&serde_json::Value)> = match args.command {
+        Command::RateLimits => Some(print_rate_limits),
+        Command::Rules {
+            command: RulesCommand::List,
+        } => Some(print_rule_list),
+        Command::Rules {
+            command: RulesCommand::Test { .. },
+        } => Some(print_rule_test),
+        _ => None,
+    };
+
     let command = match args.command {
         Command::Dismiss { id } => IpcCommand::Dismiss { id },
         Command::DismissAll => IpcCommand::DismissAll,
@@ -136,6 +267,28 @@ fn main() -> Result<()> {
         Command::Reload => IpcCommand::ReloadConfig,
         Command::List => IpcCommand::GetNotifications,
         Command::Action { id, action } => IpcCommand::InvokeAction { id, action },
+        Command::RateLimits => IpcCommand::GetRateLimits,
+        Command::Subscribe => IpcCommand::Subscribe,
+        Command::Rules { command } => match command {
+            RulesCommand::List => IpcCommand::ListRules,
+            RulesCommand::Enable { name } => IpcCommand::EnableRule { name },
+            RulesCommand::Disable { name } => IpcCommand::DisableRule { name },
+            RulesCommand::Test {
+                app,
+                summary,
+                body,
+                urgency,
+                hints,
+                actions,
+            } => IpcCommand::TestRules {
+                app_name: app,
+                summary,
+                body,
+                urgency,
+                hints: hints.into_iter().collect(),
+                actions,
+            },
+        },
     };
 
     let response = send_command(&socket_path, command)?;
@@ -152,6 +305,11 @@ fn main() -> Result<()> {
         // Human-readable output
         if response.success {
             if let Some(data) = response.data {
+                if let Some(print) = printer {
+                    print(&data);
+                    return Ok(());
+                }
+
                 match data {
                     serde_json::Value::Bool(b) => {
                         println!("{}", if b { "enabled" } else { "disabled" });
@@ -190,3 +348,165 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Print the result of `rules list` in a readable form
+fn print_rule_list(data: &serde_json::Value) {
+    let rules = data.as_array().cloned().unwrap_or_default();
+    if rules.is_empty() {
+        println!("No rules configured");
+        return;
+    }
+
+    for rule in &rules {
+        let index = rule.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+        let name = rule.get("name").and_then(|v| v.as_str()).unwrap_or("-");
+        let enabled = rule
+            .get("enabled")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        println!(
+            "#{} {} ({})",
+            index,
+            name,
+            if enabled { "enabled" } else { "disabled" }
+        );
+    }
+}
+
+/// Print the result of `rate-limits` in a readable form
+fn print_rate_limits(data: &serde_json::Value) {
+    let apps = data.as_array().cloned().unwrap_or_default();
+    if apps.is_empty() {
+        println!("No apps rate limited");
+        return;
+    }
+
+    for app in &apps {
+        let name = app.get("app_name").and_then(|v| v.as_str()).unwrap_or("");
+        let throttled = app
+            .get("throttled")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let dropped = app.get("dropped").and_then(|v| v.as_u64()).unwrap_or(0);
+        let total = app
+            .get("total_dropped")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        println!(
+            "{} ({}): {} dropped, {} total",
+            name,
+            if throttled { "throttled" } else { "ok" },
+            dropped,
+            total
+        );
+    }
+}
+
+/// Print the result of `rules test` in a readable form
+fn print_rule_test(data: &serde_json::Value) {
+    let matched = data
+        .get("matched")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    if matched.is_empty() {
+        println!("No rules matched");
+    } else {
+        println!("Matched rules (in order):");
+        for rule in &matched {
+            let index = rule.get("index").and_then(|v| v.as_u64()).unwrap_or(0);
+            let criteria = rule
+                .get("criteria")
+                .map(|v| v.to_string())
+                .unwrap_or_default();
+            match rule.get("name").and_then(|v| v.as_str()) {
+                Some(name) => println!("  #{} {} {}", index, name, criteria),
+                None => println!("  #{} {}", index, criteria),
+            }
+        }
+    }
+
+    let field = |key: &str| match data.get(key) {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Bool(b)) => (if *b { "yes" } else { "no" }).to_string(),
+        Some(serde_json::Value::Null) | None => "-".to_string(),
+        Some(other) => other.to_string(),
+    };
+
+    println!();
+    println!("Effective settings:");
+    println!("  timeout:  {} ms", field("timeout"));
+    println!("  urgency:  {}", field("urgency"));
+    println!("  anchor:   {}", field("anchor"));
+    println!("  display:  {}", field("display"));
+    println!("  history:  {}", field("history"));
+    println!("  sound:    {}", field("sound"));
+    println!("  css:      {}", field("css_class"));
+    println!("  icon:     {}", field("icon"));
+    println!("  app name: {}", field("app_name"));
+    println!("  group:    {}", field("group"));
+    println!("  per app:  {}", field("max_visible_per_app"));
+    match data.get("escalate") {
+        Some(policy) if !policy.is_null() => println!(
+            "  escalate: {} every {} s, {} times",
+            policy.get("action").and_then(|v| v.as_str()).unwrap_or(""),
+            policy.get("after").and_then(|v| v.as_u64()).unwrap_or(0),
+            policy.get("times").and_then(|v| v.as_u64()).unwrap_or(0)
+        ),
+        _ => println!("  escalate: -"),
+    }
+    println!("  summary:  {}", field("summary"));
+    println!("  body:     {}", field("body"));
+    if data.get("sound_file").is_some_and(|v| !v.is_null()) {
+        println!("  sound file: {}", field("sound_file"));
+    }
+    if data.get("exec").is_some_and(|v| !v.is_null()) {
+        println!("  exec:     {}", field("exec"));
+    }
+    if data.get("invoke_action").is_some_and(|v| !v.is_null()) {
+        println!(
+            "  invoke:   {} after {} ms",
+            field("invoke_action"),
+            data.get("invoke_delay")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0)
+        );
+    }
+}
+
+/// Print one streamed event in a readable form
+fn print_event(event: &serde_json::Value) {
+    let field = |key: &str| event.get(key).and_then(|v| v.as_str()).unwrap_or("");
+    let id = event.get("id").and_then(|v| v.as_u64()).unwrap_or(0);
+
+    match field("event") {
+        "added" | "updated" | "shown" => println!(
+            "{} [{}] {} - {} ({})",
+            field("event"),
+            id,
+            field("app"),
+            field("summary"),
+            field("urgency")
+        ),
+        "closed" => println!("closed [{}] {} ({})", id, field("app"), field("reason")),
+        "action_invoked" => println!("action [{}] {}", id, field("action")),
+        "replied" => println!("reply [{}] {}", id, field("text")),
+        "escalated" => println!(
+            "escalated [{}] {} - {} ({}, round {})",
+            id,
+            field("app"),
+            field("summary"),
+            field("action"),
+            event.get("round").and_then(|v| v.as_u64()).unwrap_or(0)
+        ),
+        "dnd_changed" => {
+            let enabled = event
+                .get("enabled")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            println!("dnd {}", if enabled { "enabled" } else { "disabled" });
+        }
+        other => println!("{}", other),
+    }
+}
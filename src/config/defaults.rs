@@ -44,6 +44,17 @@ pub const DEFAULT_CSS: &str = r#"
     margin-bottom: 4px;
 }
 
+/* Repeat counter of merged duplicates */
+.notification .repeat-count {
+    font-size: 11px;
+    font-weight: 600;
+    color: #1e1e2e;
+    background-color: rgba(137, 180, 250, 0.85);
+    border-radius: 8px;
+    padding: 0 6px;
+    margin-bottom: 4px;
+}
+
 /* Body text */
 .notification .body {
     font-size: 13px;
@@ -148,6 +159,27 @@ window {
     background: rgba(137, 180, 250, 0.3);
 }
 
+/* "+N more" indicator for pending notifications */
+.notification.overflow {
+    padding: 6px 12px;
+    font-size: 12px;
+    color: rgba(205, 214, 244, 0.7);
+}
+
+/* "N more" button of grouped popups */
+.notification-stack .stack-toggle {
+    padding: 4px 12px;
+    font-size: 12px;
+    color: rgba(205, 214, 244, 0.8);
+    background: rgba(30, 30, 46, 0.9);
+    border: none;
+    border-radius: 8px;
+}
+
+.notification-stack .stack-toggle:hover {
+    background: rgba(49, 50, 68, 0.95);
+}
+
 /* Notification Center */
 .notification-center {
     background: linear-gradient(180deg, rgba(24, 24, 37, 0.95) 0%, rgba(30, 30, 46, 0.98) 100%);
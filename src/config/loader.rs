@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use tracing::{debug, info, warn};
 
 use super::Config;
+use crate::rules::RuleSet;
 
 pub struct ConfigLoader;
 
@@ -24,7 +25,9 @@ impl ConfigLoader {
     }
 
     /// Load configuration from the default path or create default
-    pub fn load() -> Result<Config> {
+    ///
+    /// Returns the config together with its compiled rules.
+    pub fn load() -> Result<(Config, RuleSet)> {
         if let Some(path) = Self::config_path() {
             if path.exists() {
                 return Self::load_from_path(&path);
@@ -32,11 +35,13 @@ impl ConfigLoader {
                 debug!("Config file not found at {:?}, using defaults", path);
             }
         }
-        Ok(Config::default())
+        Ok((Config::default(), RuleSet::default()))
     }
 
     /// Load configuration from a specific path
-    pub fn load_from_path(path: &PathBuf) -> Result<Config> {
+    ///
+    /// Rules are compiled here, so mistakes surface at load time.
+    pub fn load_from_path(path: &PathBuf) -> Result<(Config, RuleSet)> {
         info!("Loading configuration from {:?}", path);
         let content = std::fs::read_to_string(path)
             .with_context(|| format!("Failed to read config file: {:?}", path))?;
@@ -44,7 +49,10 @@ impl ConfigLoader {
         let config: Config = toml::from_str(&content)
             .with_context(|| format!("Failed to parse config file: {:?}", path))?;
 
-        Ok(config)
+        let rules = RuleSet::compile(&config.rules)
+            .with_context(|| format!("Invalid rule in config file: {:?}", path))?;
+
+        Ok((config, rules))
     }
 
     /// Save configuration to the default path
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 /// Main configuration structure
@@ -13,6 +14,9 @@ pub struct Config {
     pub dnd: DndConfig,
     pub sound: SoundConfig,
     pub ipc: IpcConfig,
+    pub script: ScriptConfig,
+    pub rate_limit: RateLimitConfig,
+    pub escalation: EscalationConfig,
     #[serde(default)]
     pub rules: Vec<AppRule>,
 }
@@ -20,7 +24,7 @@ pub struct Config {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct GeneralConfig {
-    /// Maximum number of visible notifications
+    /// Maximum number of visible notifications per anchor
     pub max_visible: u32,
     /// Sort order for notifications
     pub sort_order: SortOrder,
@@ -28,6 +32,15 @@ pub struct GeneralConfig {
     pub markup: bool,
     /// Idle threshold in seconds (pause timeouts when idle)
     pub idle_threshold: Option<u64>,
+    /// Seconds within which an identical notification from the same app
+    /// updates the existing popup with a repeat counter (0 = disabled)
+    pub duplicate_window: u64,
+    /// Collapse popups from the same app (or rule `group`) into one stack
+    pub group_popups: bool,
+    /// Maximum number of popups one app may have on screen (0 = no limit)
+    pub max_visible_per_app: u32,
+    /// What happens to notifications of an app that is at its limit
+    pub per_app_overflow: PerAppOverflow,
 }
 
 impl Default for GeneralConfig {
@@ -37,6 +50,10 @@ impl Default for GeneralConfig {
             sort_order: SortOrder::NewestFirst,
             markup: true,
             idle_threshold: None,
+            duplicate_window: 10,
+            group_popups: false,
+            max_visible_per_app: 0,
+            per_app_overflow: PerAppOverflow::Queue,
         }
     }
 }
@@ -187,6 +204,81 @@ impl Default for TimeoutConfig {
     }
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Throttle apps that send too many notifications
+    pub enabled: bool,
+    /// Notifications an app may send in a burst
+    pub burst: u32,
+    /// Notifications per second an app may send after a burst
+    pub per_second: f64,
+    /// Limits for specific apps, by app name
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub apps: HashMap<String, AppRateLimit>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            burst: 20,
+            per_second: 5.0,
+            apps: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct AppRateLimit {
+    pub burst: u32,
+    pub per_second: f64,
+}
+
+/// Re-alerting of notifications nobody reacted to, by urgency
+///
+/// Nothing escalates unless a policy is set here or by a rule.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EscalationConfig {
+    pub low: Option<EscalationPolicy>,
+    pub normal: Option<EscalationPolicy>,
+    pub critical: Option<EscalationPolicy>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EscalationPolicy {
+    /// Seconds without interaction before each escalation
+    pub after: u64,
+    /// How many times to escalate (0 = never)
+    pub times: u32,
+    /// What to do when escalating
+    pub action: EscalationAction,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        Self {
+            after: 300,
+            times: 3,
+            action: EscalationAction::Reshow,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum EscalationAction {
+    /// Pop the notification up again, even if it had expired
+    #[default]
+    Reshow,
+    /// Pop it up again as critical
+    Critical,
+    /// Play a reminder sound (needs `[sound] enabled` and the `sound` feature)
+    Sound,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct HistoryConfig {
@@ -208,7 +300,7 @@ impl Default for HistoryConfig {
     }
 }
 
-#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct DndConfig {
     /// Start time for scheduled DND (HH:MM format)
@@ -217,6 +309,22 @@ pub struct DndConfig {
     pub schedule_end: Option<String>,
     /// Days of week for scheduled DND
     pub schedule_days: Vec<Weekday>,
+    /// Let critical notifications pop up during DND
+    pub allow_critical: bool,
+    /// Show how many notifications were suppressed when DND ends
+    pub summary: bool,
+}
+
+impl Default for DndConfig {
+    fn default() -> Self {
+        Self {
+            schedule_start: None,
+            schedule_end: None,
+            schedule_days: Vec::new(),
+            allow_critical: true,
+            summary: true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -239,8 +347,32 @@ pub struct IpcConfig {
     pub socket_path: Option<PathBuf>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ScriptConfig {
+    /// Rhai script run on every notification (needs the `scripting` feature)
+    pub path: Option<PathBuf>,
+    /// Time limit for one run of the script in milliseconds
+    pub timeout: u64,
+}
+
+impl Default for ScriptConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            timeout: 50,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AppRule {
+    /// Name used to enable or disable the rule at runtime
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Stop evaluating later rules once this one matches
+    #[serde(default, rename = "final", alias = "stop")]
+    pub stop: bool,
     /// Match criteria
     pub criteria: RuleCriteria,
     /// Actions to apply
@@ -255,6 +387,26 @@ pub struct RuleCriteria {
     pub body: Option<String>,
     pub urgency: Option<String>,
     pub category: Option<String>,
+    /// Desktop entry name
+    pub desktop_entry: Option<String>,
+    /// Hint values by hint name (e.g. `x-kde-origin-name`)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub hints: HashMap<String, String>,
+    /// Action key the notification must offer
+    pub has_action: Option<String>,
+    /// Transient flag
+    pub transient: Option<bool>,
+    /// Resident flag
+    pub resident: Option<bool>,
+    /// Only match from this local time (HH:MM)
+    pub time_start: Option<String>,
+    /// Only match until this local time (HH:MM)
+    pub time_end: Option<String>,
+    /// Criteria that must not match
+    pub not: Option<Box<RuleCriteria>>,
+    /// At least one of these criteria must match
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub any: Vec<RuleCriteria>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -265,12 +417,67 @@ pub struct RuleActions {
     pub skip_history: Option<bool>,
     pub skip_sound: Option<bool>,
     pub css_class: Option<String>,
+    /// Show, drop or only record the notification
+    pub display: Option<DisplayMode>,
+    /// Shell command to run with the notification in its environment
+    pub exec: Option<String>,
+    /// Time in milliseconds before the command is killed
+    pub exec_timeout: Option<u64>,
+    /// Action key to invoke automatically (`default` for the default action)
+    pub invoke_action: Option<String>,
+    /// Delay in milliseconds before the action is invoked
+    pub invoke_delay: Option<u64>,
+    /// Icon name or path to show instead of the notification's own
+    pub icon: Option<String>,
+    /// App name to show instead of the one the app sent
+    pub app_display_name: Option<String>,
+    /// Sound file or sound theme name to play
+    pub sound: Option<String>,
+    /// Stack popups under this key instead of the app name
+    pub group: Option<String>,
+    /// Popup limit for the matched notifications' app (0 = no limit)
+    pub max_visible_per_app: Option<u32>,
+    /// Escalation instead of the one for the notification's urgency
+    pub escalate: Option<EscalationPolicy>,
+    /// Regex substitutions on summary/body (accumulated across rules)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rewrite: Vec<RewriteRule>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RewriteRule {
+    /// Regex to replace
+    pub pattern: String,
+    /// Replacement text (`$1` refers to capture groups)
+    #[serde(default)]
+    pub replacement: String,
+    /// Which text to rewrite
+    #[serde(default)]
+    pub field: RewriteField,
+    /// Where the rewritten text is used
+    #[serde(default)]
+    pub target: RewriteTarget,
 }
 
 // Enums
 
+/// How a notification is presented once rules have run
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
 #[serde(rename_all = "kebab-case")]
+pub enum DisplayMode {
+    /// Show a popup as usual
+    #[default]
+    Show,
+    /// Accept the notification but neither show nor record it
+    #[serde(alias = "hide")]
+    Drop,
+    /// Record the notification in history without showing a popup
+    #[serde(alias = "history_only")]
+    HistoryOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, Default)]
+#[serde(rename_all = "kebab-case")]
 pub enum Anchor {
     TopLeft,
     TopCenter,
@@ -281,6 +488,26 @@ pub enum Anchor {
     BottomRight,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RewriteField {
+    Summary,
+    Body,
+    #[default]
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RewriteTarget {
+    /// Only the popup shows the rewritten text
+    Popup,
+    /// Only history stores the rewritten text
+    History,
+    #[default]
+    Both,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub enum Layer {
@@ -309,6 +536,18 @@ pub enum SortOrder {
     UrgencyDescending,
 }
 
+/// Handling of notifications beyond an app's `max_visible_per_app`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PerAppOverflow {
+    /// Wait in the pending queue until one of the app's popups closes
+    #[default]
+    Queue,
+    /// Show the newest in place of the app's latest popup, which goes back
+    /// to the queue
+    Collapse,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub enum MonitorSelection {
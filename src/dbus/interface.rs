@@ -7,28 +7,19 @@ use zbus::object_server::SignalEmitter;
 use zbus::zvariant::{OwnedValue, Value};
 
 use super::types::{ServerInfo, CAPABILITIES};
-use crate::history::{HistoryEntry, HistoryStore};
 use crate::notification::{
     ImageData, Notification, NotificationHints, NotificationManager, Urgency,
 };
+use crate::rules::{spawn_rule_command, DEFAULT_EXEC_TIMEOUT_MS};
 
 /// D-Bus notification server implementing org.freedesktop.Notifications
 pub struct NotificationServer {
     manager: Arc<NotificationManager>,
-    history_store: Option<Arc<HistoryStore>>,
 }
 
 impl NotificationServer {
     pub fn new(manager: Arc<NotificationManager>) -> Self {
-        Self {
-            manager,
-            history_store: None,
-        }
-    }
-
-    pub fn with_history(mut self, store: Arc<HistoryStore>) -> Self {
-        self.history_store = Some(store);
-        self
+        Self { manager }
     }
 
     /// Parse hints from D-Bus variant dictionary
@@ -113,7 +104,12 @@ impl NotificationServer {
                     result.inline_reply = true;
                 }
                 _ => {
-                    debug!("Unknown hint: {}", key);
+                    // Keep simple values around so rules can match on them
+                    if let Some(s) = Self::hint_to_string(&value) {
+                        result.extra.insert(key, s);
+                    } else {
+                        debug!("Unknown hint: {}", key);
+                    }
                 }
             }
         }
@@ -121,6 +117,24 @@ impl NotificationServer {
         result
     }
 
+    /// Render a simple hint value (string, number or bool) as a string
+    fn hint_to_string(value: &Value) -> Option<String> {
+        match value {
+            Value::Str(s) => Some(s.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            Value::U8(n) => Some(n.to_string()),
+            Value::I16(n) => Some(n.to_string()),
+            Value::U16(n) => Some(n.to_string()),
+            Value::I32(n) => Some(n.to_string()),
+            Value::U32(n) => Some(n.to_string()),
+            Value::I64(n) => Some(n.to_string()),
+            Value::U64(n) => Some(n.to_string()),
+            Value::F64(n) => Some(n.to_string()),
+            Value::Value(inner) => Self::hint_to_string(inner),
+            _ => None,
+        }
+    }
+
     /// Parse image data from D-Bus variant
     fn parse_image_data(value: &Value) -> Option<ImageData> {
         // Image data is a structure: (iiibiiay)
@@ -185,46 +199,43 @@ impl NotificationServer {
 
         let parsed_hints = Self::parse_hints(hints);
 
-        let notification = Notification::new(
+        let mut notification = Notification::new(
             0, // Will be assigned by manager
             app_name.to_string(),
             replaces_id,
             app_icon.to_string(),
             summary.to_string(),
             body.to_string(),
-            actions.clone(),
-            parsed_hints.clone(),
+            actions,
+            parsed_hints,
             expire_timeout,
         );
 
-        let id = self.manager.add_notification(notification).await;
+        // Run per-app rules before the notification is stored
+        let rules = self.manager.apply_rules(&mut notification);
+        self.manager.apply_script(&mut notification);
 
-        // Save to history if not transient
-        if !parsed_hints.transient {
-            if let Some(ref store) = self.history_store {
-                let entry = HistoryEntry {
-                    id,
-                    app_name: app_name.to_string(),
-                    summary: summary.to_string(),
-                    body: body.to_string(),
-                    icon: if app_icon.is_empty() {
-                        None
-                    } else {
-                        Some(app_icon.to_string())
-                    },
-                    urgency: parsed_hints.urgency.to_string(),
-                    timestamp: chrono::Utc::now(),
-                    actions: actions
-                        .chunks(2)
-                        .filter_map(|c| c.first().cloned())
-                        .collect(),
-                    dismissed: false,
-                    expired: false,
-                };
-                if let Err(e) = store.add(&entry) {
-                    debug!("Failed to save notification to history: {}", e);
-                }
-            }
+        // Floods are counted in a summary popup instead of stored
+        if let Some(id) = self.manager.throttle(&notification).await {
+            return id;
+        }
+
+        let id = self.manager.add_notification(notification.clone()).await;
+        notification.id = id;
+
+        // Run the rule's external command without blocking the D-Bus call
+        if let Some(ref command) = rules.actions.exec {
+            let timeout = rules
+                .actions
+                .exec_timeout
+                .unwrap_or(DEFAULT_EXEC_TIMEOUT_MS);
+            spawn_rule_command(command, timeout, &notification);
+        }
+
+        // Auto-invoke an action; it goes through the same path as a click
+        if let Some(ref action_key) = rules.actions.invoke_action {
+            let delay = rules.actions.invoke_delay.unwrap_or(0);
+            self.manager.schedule_action(id, action_key.clone(), delay);
         }
 
         id
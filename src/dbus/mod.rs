@@ -4,8 +4,6 @@ mod types;
 
 #[allow(unused_imports)]
 pub use interface::NotificationServer;
-#[allow(unused_imports)]
 pub use server::start_dbus_server;
-pub use server::start_dbus_server_with_history;
 #[allow(unused_imports)]
 pub use types::{CloseReason, ServerInfo, CAPABILITIES};
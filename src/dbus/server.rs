@@ -1,39 +1,18 @@
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
-use async_channel::Receiver;
 use tracing::info;
 use zbus::connection::Builder;
 use zbus::Connection;
 
 use super::interface::NotificationServer;
-use crate::history::HistoryStore;
-use crate::notification::{ActionEvent, CloseReason, NotificationManager};
+use crate::notification::{NotificationEvent, NotificationManager};
 
 /// Start the D-Bus notification server
-pub async fn start_dbus_server(
-    manager: Arc<NotificationManager>,
-    close_receiver: Receiver<(u32, CloseReason)>,
-    action_receiver: Receiver<ActionEvent>,
-) -> Result<Connection> {
-    start_dbus_server_with_history(manager, close_receiver, action_receiver, None).await
-}
-
-/// Start the D-Bus notification server with history storage
-pub async fn start_dbus_server_with_history(
-    manager: Arc<NotificationManager>,
-    close_receiver: Receiver<(u32, CloseReason)>,
-    action_receiver: Receiver<ActionEvent>,
-    history_store: Option<Arc<HistoryStore>>,
-) -> Result<Connection> {
+pub async fn start_dbus_server(manager: Arc<NotificationManager>) -> Result<Connection> {
     info!("Starting D-Bus notification server...");
 
-    let server = NotificationServer::new(manager);
-    let server = if let Some(store) = history_store {
-        server.with_history(store)
-    } else {
-        server
-    };
+    let server = NotificationServer::new(manager.clone());
 
     let connection = Builder::session()
         .context("Failed to connect to session bus")?
@@ -48,68 +27,57 @@ pub async fn start_dbus_server_with_history(
     info!("D-Bus server started successfully");
     info!("Registered as org.freedesktop.Notifications");
 
-    // Spawn task to handle close events and emit NotificationClosed signals
-    let close_conn = connection.clone();
+    // Emit D-Bus signals for lifecycle events; every close must be signalled
+    let events = manager.subscribe_lossless();
+    let signal_conn = connection.clone();
     tokio::spawn(async move {
-        while let Ok((id, reason)) = close_receiver.recv().await {
+        while let Ok(event) = events.recv().await {
+            emit_signal(&signal_conn, event).await;
+        }
+    });
+
+    Ok(connection)
+}
+
+/// Emit the D-Bus signal for an event, if it has one
+async fn emit_signal(connection: &Connection, event: NotificationEvent) {
+    // Get a fresh interface reference for each event
+    let Ok(iface_ref) = connection
+        .object_server()
+        .interface::<_, NotificationServer>("/org/freedesktop/Notifications")
+        .await
+    else {
+        return;
+    };
+    let emitter = iface_ref.signal_emitter();
+
+    let result = match event {
+        NotificationEvent::Closed {
+            notification,
+            reason,
+        } => {
             info!(
                 "Emitting NotificationClosed signal: id={}, reason={:?}",
-                id, reason
+                notification.id, reason
             );
-            // Get fresh object server reference for each event
-            let object_server = close_conn.object_server();
-            // Get interface reference to emit signal
-            if let Ok(iface_ref) = object_server
-                .interface::<_, NotificationServer>("/org/freedesktop/Notifications")
-                .await
-            {
-                if let Err(e) = NotificationServer::notification_closed(
-                    iface_ref.signal_emitter(),
-                    id,
-                    reason as u32,
-                )
-                .await
-                {
-                    tracing::error!("Failed to emit NotificationClosed signal: {}", e);
-                }
-            }
+            NotificationServer::notification_closed(emitter, notification.id, reason as u32).await
         }
-    });
-
-    // Spawn task to handle action events and emit ActionInvoked signals
-    let action_conn = connection.clone();
-    tokio::spawn(async move {
-        while let Ok(event) = action_receiver.recv().await {
-            match event {
-                ActionEvent::ActionInvoked { id, action_key } => {
-                    info!(
-                        "Emitting ActionInvoked signal: id={}, action={}",
-                        id, action_key
-                    );
-                    // Get fresh object server reference for each event
-                    let object_server = action_conn.object_server();
-                    // Get interface reference to emit signal
-                    if let Ok(iface_ref) = object_server
-                        .interface::<_, NotificationServer>("/org/freedesktop/Notifications")
-                        .await
-                    {
-                        if let Err(e) = NotificationServer::action_invoked(
-                            iface_ref.signal_emitter(),
-                            id,
-                            action_key,
-                        )
-                        .await
-                        {
-                            tracing::error!("Failed to emit ActionInvoked signal: {}", e);
-                        }
-                    }
-                }
-                _ => {
-                    // Other action events (dismissed, hover, etc.) are handled elsewhere
-                }
-            }
+        NotificationEvent::ActionInvoked { id, action_key } => {
+            info!(
+                "Emitting ActionInvoked signal: id={}, action={}",
+                id, action_key
+            );
+            NotificationServer::action_invoked(emitter, id, action_key).await
         }
-    });
+        NotificationEvent::Replied { id, text } => {
+            // Sent as an action, since we don't advertise the inline-reply capability
+            info!("Emitting ActionInvoked signal for inline reply: id={}", id);
+            NotificationServer::action_invoked(emitter, id, format!("inline-reply:{}", text)).await
+        }
+        _ => Ok(()),
+    };
 
-    Ok(connection)
+    if let Err(e) = result {
+        tracing::error!("Failed to emit D-Bus signal: {}", e);
+    }
 }
@@ -1,4 +1,5 @@
 use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::watch;
 use tracing::info;
 
 /// Do Not Disturb state
@@ -6,6 +7,8 @@ pub struct DndState {
     enabled: AtomicBool,
     /// Whether DND was enabled manually (not by schedule)
     manual: AtomicBool,
+    /// Broadcasts the enabled flag whenever it changes
+    changes: watch::Sender<bool>,
 }
 
 impl Default for DndState {
@@ -19,9 +22,25 @@ impl DndState {
         Self {
             enabled: AtomicBool::new(false),
             manual: AtomicBool::new(false),
+            changes: watch::Sender::new(false),
         }
     }
 
+    /// Watch DND being turned on and off
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.changes.subscribe()
+    }
+
+    /// Publish the current state to subscribers, if it changed
+    fn publish(&self) {
+        let enabled = self.is_enabled();
+        self.changes.send_if_modified(|current| {
+            let changed = *current != enabled;
+            *current = enabled;
+            changed
+        });
+    }
+
     /// Check if DND is currently enabled
     pub fn is_enabled(&self) -> bool {
         self.enabled.load(Ordering::SeqCst)
@@ -31,6 +50,7 @@ impl DndState {
     pub fn enable(&self) {
         self.enabled.store(true, Ordering::SeqCst);
         self.manual.store(true, Ordering::SeqCst);
+        self.publish();
         info!("Do Not Disturb enabled");
     }
 
@@ -38,6 +58,7 @@ impl DndState {
     pub fn disable(&self) {
         self.enabled.store(false, Ordering::SeqCst);
         self.manual.store(false, Ordering::SeqCst);
+        self.publish();
         info!("Do Not Disturb disabled");
     }
 
@@ -46,6 +67,7 @@ impl DndState {
         let current = self.enabled.load(Ordering::SeqCst);
         self.enabled.store(!current, Ordering::SeqCst);
         self.manual.store(!current, Ordering::SeqCst);
+        self.publish();
         info!("Do Not Disturb toggled to {}", !current);
     }
 
@@ -53,6 +75,7 @@ impl DndState {
     pub fn enable_scheduled(&self) {
         if !self.manual.load(Ordering::SeqCst) {
             self.enabled.store(true, Ordering::SeqCst);
+            self.publish();
             info!("Do Not Disturb enabled by schedule");
         }
     }
@@ -61,6 +84,7 @@ impl DndState {
     pub fn disable_scheduled(&self) {
         if !self.manual.load(Ordering::SeqCst) {
             self.enabled.store(false, Ordering::SeqCst);
+            self.publish();
             info!("Do Not Disturb disabled by schedule");
         }
     }
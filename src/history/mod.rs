@@ -1,6 +1,8 @@
 mod store;
+mod writer;
 
 pub use store::HistoryStore;
+pub use writer::HistoryWriter;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -18,6 +20,16 @@ pub struct HistoryEntry {
     pub actions: Vec<String>,
     pub dismissed: bool,
     pub expired: bool,
+    /// How many identical notifications this entry stands for
+    #[serde(default = "default_repeat_count")]
+    pub repeat_count: u32,
+    /// Key of the database row, once stored
+    #[serde(default)]
+    pub row_id: Option<i64>,
+}
+
+fn default_repeat_count() -> u32 {
+    1
 }
 
 impl HistoryEntry {
@@ -41,6 +53,8 @@ impl HistoryEntry {
             actions,
             dismissed: false,
             expired: false,
+            repeat_count: 1,
+            row_id: None,
         }
     }
 }
@@ -60,11 +60,25 @@ impl HistoryStore {
                 timestamp TEXT NOT NULL,
                 actions TEXT DEFAULT '[]',
                 dismissed INTEGER DEFAULT 0,
-                expired INTEGER DEFAULT 0
+                expired INTEGER DEFAULT 0,
+                repeat_count INTEGER DEFAULT 1
             )",
             [],
         )?;
 
+        // Databases created before repeat counting lack the column
+        let has_repeat_count = conn
+            .prepare(
+                "SELECT 1 FROM pragma_table_info('notifications') WHERE name = 'repeat_count'",
+            )?
+            .exists([])?;
+        if !has_repeat_count {
+            conn.execute(
+                "ALTER TABLE notifications ADD COLUMN repeat_count INTEGER DEFAULT 1",
+                [],
+            )?;
+        }
+
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_timestamp ON notifications(timestamp DESC)",
             [],
@@ -75,16 +89,72 @@ impl HistoryStore {
             [],
         )?;
 
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS meta (
+                key TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// Highest notification ID handed out so far, across restarts
+    ///
+    /// Databases from before the counter was stored fall back to the highest
+    /// ID in history.
+    pub fn last_notification_id(&self) -> Result<u32> {
+        let conn = self.conn.lock().unwrap();
+        let last: i64 = conn.query_row(
+            "SELECT MAX(
+                COALESCE((SELECT value FROM meta WHERE key = 'last_notification_id'), 0),
+                COALESCE((SELECT MAX(notification_id) FROM notifications), 0)
+            )",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(last.clamp(0, u32::MAX as i64) as u32)
+    }
+
+    /// Record a notification ID as handed out
+    ///
+    /// The stored counter never goes down, even if IDs are recorded out of
+    /// order.
+    pub fn set_last_notification_id(&self, id: u32) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO meta (key, value) VALUES ('last_notification_id', ?1)
+             ON CONFLICT(key) DO UPDATE SET value = MAX(value, excluded.value)",
+            params![id],
+        )?;
         Ok(())
     }
 
-    /// Add a notification to history
-    pub fn add(&self, entry: &HistoryEntry) -> Result<()> {
+    /// Add a notification to history and return the key of its row
+    ///
+    /// A repeat of a notification already in history (`row_id` set) updates
+    /// the count and time of its row instead of adding another one.
+    pub fn add(&self, entry: &HistoryEntry) -> Result<i64> {
         let conn = self.conn.lock().unwrap();
 
+        if let (Some(row_id), true) = (entry.row_id, entry.repeat_count > 1) {
+            let updated = conn.execute(
+                "UPDATE notifications SET repeat_count = ?1, timestamp = ?2 WHERE id = ?3",
+                params![entry.repeat_count, entry.timestamp.to_rfc3339(), row_id],
+            )?;
+            if updated > 0 {
+                debug!(
+                    "Notification {} repeated {} times in history",
+                    entry.id, entry.repeat_count
+                );
+                return Ok(row_id);
+            }
+        }
+
         conn.execute(
-            "INSERT INTO notifications (notification_id, app_name, summary, body, icon, urgency, timestamp, actions, dismissed, expired)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            "INSERT INTO notifications (notification_id, app_name, summary, body, icon, urgency, timestamp, actions, dismissed, expired, repeat_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             params![
                 entry.id,
                 entry.app_name,
@@ -96,8 +166,10 @@ impl HistoryStore {
                 serde_json::to_string(&entry.actions).unwrap_or_default(),
                 entry.dismissed as i32,
                 entry.expired as i32,
+                entry.repeat_count,
             ],
         )?;
+        let row_id = conn.last_insert_rowid();
 
         debug!("Added notification {} to history", entry.id);
 
@@ -105,25 +177,25 @@ impl HistoryStore {
         drop(conn);
         self.cleanup()?;
 
-        Ok(())
+        Ok(row_id)
     }
 
-    /// Mark a notification as dismissed
-    pub fn mark_dismissed(&self, notification_id: u32) -> Result<()> {
+    /// Mark a history entry as dismissed
+    pub fn mark_dismissed(&self, row_id: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE notifications SET dismissed = 1 WHERE notification_id = ?1",
-            params![notification_id],
+            "UPDATE notifications SET dismissed = 1 WHERE id = ?1",
+            params![row_id],
         )?;
         Ok(())
     }
 
-    /// Mark a notification as expired
-    pub fn mark_expired(&self, notification_id: u32) -> Result<()> {
+    /// Mark a history entry as expired
+    pub fn mark_expired(&self, row_id: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE notifications SET expired = 1 WHERE notification_id = ?1",
-            params![notification_id],
+            "UPDATE notifications SET expired = 1 WHERE id = ?1",
+            params![row_id],
         )?;
         Ok(())
     }
@@ -132,7 +204,7 @@ impl HistoryStore {
     pub fn get_all(&self) -> Result<Vec<HistoryEntry>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT notification_id, app_name, summary, body, icon, urgency, timestamp, actions, dismissed, expired
+            "SELECT notification_id, app_name, summary, body, icon, urgency, timestamp, actions, dismissed, expired, repeat_count, id
              FROM notifications ORDER BY timestamp DESC"
         )?;
 
@@ -156,6 +228,8 @@ impl HistoryStore {
                 actions,
                 dismissed: row.get::<_, i32>(8)? != 0,
                 expired: row.get::<_, i32>(9)? != 0,
+                repeat_count: row.get::<_, Option<u32>>(10)?.unwrap_or(1),
+                row_id: Some(row.get(11)?),
             })
         })?;
 
@@ -166,7 +240,7 @@ impl HistoryStore {
     pub fn get_by_app(&self, app_name: &str) -> Result<Vec<HistoryEntry>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT notification_id, app_name, summary, body, icon, urgency, timestamp, actions, dismissed, expired
+            "SELECT notification_id, app_name, summary, body, icon, urgency, timestamp, actions, dismissed, expired, repeat_count, id
              FROM notifications WHERE app_name = ?1 ORDER BY timestamp DESC"
         )?;
 
@@ -190,6 +264,8 @@ impl HistoryStore {
                 actions,
                 dismissed: row.get::<_, i32>(8)? != 0,
                 expired: row.get::<_, i32>(9)? != 0,
+                repeat_count: row.get::<_, Option<u32>>(10)?.unwrap_or(1),
+                row_id: Some(row.get(11)?),
             })
         })?;
 
@@ -228,12 +304,9 @@ impl HistoryStore {
     }
 
     /// Delete a specific entry
-    pub fn delete(&self, notification_id: u32) -> Result<()> {
+    pub fn delete(&self, row_id: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "DELETE FROM notifications WHERE notification_id = ?1",
-            params![notification_id],
-        )?;
+        conn.execute("DELETE FROM notifications WHERE id = ?1", params![row_id])?;
         Ok(())
     }
 
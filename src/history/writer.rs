@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use async_channel::Receiver;
+use tracing::debug;
+
+use super::HistoryStore;
+use crate::notification::{CloseReason, NotificationEvent, NotificationManager};
+
+/// Records how notifications ended in their history rows
+///
+/// Runs as a lossless subscriber of the manager's event stream, so every
+/// close path (user, timer, D-Bus, IPC) is covered and none is skipped under
+/// load.
+pub struct HistoryWriter {
+    store: Arc<HistoryStore>,
+    manager: Arc<NotificationManager>,
+    events: Receiver<NotificationEvent>,
+}
+
+impl HistoryWriter {
+    /// Subscribe right away, so nothing closed before [`Self::run`] is missed
+    pub fn new(store: Arc<HistoryStore>, manager: Arc<NotificationManager>) -> Self {
+        let events = manager.subscribe_lossless();
+        Self {
+            store,
+            manager,
+            events,
+        }
+    }
+
+    /// Mark the rows of notifications that were dismissed or expired
+    pub async fn run(self) {
+        while let Ok(event) = self.events.recv().await {
+            let NotificationEvent::Closed {
+                notification,
+                reason,
+            } = event
+            else {
+                continue;
+            };
+
+            // Transient notifications have no row to mark
+            let Some(row_id) = notification.history_row else {
+                continue;
+            };
+            let result = match reason {
+                CloseReason::Dismissed => self.store.mark_dismissed(row_id),
+                CloseReason::Expired => self.store.mark_expired(row_id),
+                _ => continue,
+            };
+            match result {
+                Ok(()) => self.manager.history_changed(),
+                Err(e) => debug!("Failed to update history row {}: {}", row_id, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,3 @@
+mod monitor;
+
+pub use monitor::IdleMonitor;
@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_channel::{Receiver, Sender};
+use tracing::{debug, info, warn};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{Connection, Dispatch, QueueHandle};
+use wayland_protocols::ext::idle_notify::v1::client::{
+    ext_idle_notification_v1, ext_idle_notifier_v1,
+};
+
+/// Watches user idleness through the `ext-idle-notify-v1` Wayland protocol
+///
+/// Sends `true` on the returned channel when the user has been idle for the
+/// threshold and `false` when they are back.
+pub struct IdleMonitor {
+    threshold: Duration,
+}
+
+impl IdleMonitor {
+    pub fn new(threshold: Duration) -> Self {
+        Self { threshold }
+    }
+
+    /// Connect to the compositor and watch for idleness on a background thread
+    ///
+    /// Fails if there is no Wayland connection or the compositor doesn't
+    /// support the protocol.
+    pub fn spawn(self) -> Result<Receiver<bool>> {
+        let connection =
+            Connection::connect_to_env().context("Failed to connect to Wayland display")?;
+        let (globals, mut queue) = registry_queue_init::<IdleState>(&connection)
+            .context("Failed to read Wayland globals")?;
+        let qh = queue.handle();
+
+        let seat: wl_seat::WlSeat = globals
+            .bind(&qh, 1..=1, ())
+            .context("Compositor has no wl_seat")?;
+        let notifier: ext_idle_notifier_v1::ExtIdleNotifierV1 = globals
+            .bind(&qh, 1..=1, ())
+            .context("Compositor doesn't support ext-idle-notify-v1")?;
+
+        let timeout = self.threshold.as_millis().min(u32::MAX as u128) as u32;
+        let notification = notifier.get_idle_notification(timeout, &seat, &qh, ());
+
+        let (sender, receiver) = async_channel::unbounded();
+        let mut state = IdleState { sender };
+
+        std::thread::Builder::new()
+            .name("idle-monitor".into())
+            .spawn(move || {
+                // Keep the protocol objects alive for as long as we listen
+                let _objects = (seat, notifier, notification);
+                loop {
+                    if let Err(e) = queue.blocking_dispatch(&mut state) {
+                        warn!("Idle monitor stopped: {}", e);
+                        break;
+                    }
+                    if state.sender.is_closed() {
+                        break;
+                    }
+                }
+            })
+            .context("Failed to start idle monitor thread")?;
+
+        info!(
+            "Watching for idleness after {} seconds",
+            self.threshold.as_secs()
+        );
+        Ok(receiver)
+    }
+}
+
+/// Wayland dispatch state for the idle monitor
+struct IdleState {
+    sender: Sender<bool>,
+}
+
+impl Dispatch<ext_idle_notification_v1::ExtIdleNotificationV1, ()> for IdleState {
+    fn event(
+        state: &mut Self,
+        _: &ext_idle_notification_v1::ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let idle = match event {
+            ext_idle_notification_v1::Event::Idled => true,
+            ext_idle_notification_v1::Event::Resumed => false,
+            _ => return,
+        };
+        debug!("User is {}", if idle { "idle" } else { "active" });
+        let _ = state.sender.send_blocking(idle);
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for IdleState {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for IdleState {
+    fn event(
+        _: &mut Self,
+        _: &wl_seat::WlSeat,
+        _: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ext_idle_notifier_v1::ExtIdleNotifierV1, ()> for IdleState {
+    fn event(
+        _: &mut Self,
+        _: &ext_idle_notifier_v1::ExtIdleNotifierV1,
+        _: ext_idle_notifier_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
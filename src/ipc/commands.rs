@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 /// IPC commands that can be sent to the daemon
@@ -48,6 +50,38 @@ pub enum IpcCommand {
 
     /// Invoke an action on a notification
     InvokeAction { id: u32, action: String },
+
+    /// List configured rules and whether they are enabled
+    ListRules,
+
+    /// Enable the rules with this name
+    EnableRule { name: String },
+
+    /// Disable the rules with this name
+    DisableRule { name: String },
+
+    /// Get apps that have been rate limited
+    GetRateLimits,
+
+    /// Stream notification events, one JSON object per line, until the
+    /// client disconnects
+    Subscribe,
+
+    /// Run a synthetic notification through the rules without showing it
+    TestRules {
+        #[serde(default)]
+        app_name: String,
+        #[serde(default)]
+        summary: String,
+        #[serde(default)]
+        body: String,
+        #[serde(default)]
+        urgency: Option<String>,
+        #[serde(default)]
+        hints: HashMap<String, String>,
+        #[serde(default)]
+        actions: Vec<String>,
+    },
 }
 
 /// Response from IPC commands
@@ -1,9 +1,13 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use async_channel::Sender;
+use tokio::sync::broadcast;
 
 use crate::dnd::DndState;
-use crate::notification::{NotificationManager, UiEvent};
+use crate::notification::{
+    Notification, NotificationEvent, NotificationHints, NotificationManager, UiEvent,
+};
 
 use super::commands::{IpcCommand, IpcResponse};
 
@@ -29,6 +33,11 @@ impl IpcHandler {
         self
     }
 
+    /// Receive notification events for a subscribed client
+    pub fn subscribe(&self) -> broadcast::Receiver<NotificationEvent> {
+        self.manager.subscribe()
+    }
+
     /// Handle an IPC command and return a response
     pub async fn handle(&self, command: IpcCommand) -> IpcResponse {
         match command {
@@ -74,10 +83,10 @@ impl IpcHandler {
                 IpcResponse::success()
             }
             IpcCommand::GetCount => IpcResponse::with_data(self.manager.count()),
-            IpcCommand::ReloadConfig => {
-                // TODO: Implement config reload
-                IpcResponse::success()
-            }
+            IpcCommand::ReloadConfig => match self.manager.reload_config() {
+                Ok(()) => IpcResponse::success(),
+                Err(e) => IpcResponse::error(format!("{:#}", e)),
+            },
             IpcCommand::GetNotifications => {
                 let notifications = self.manager.get_visible_notifications();
                 let summaries: Vec<_> = notifications
@@ -97,6 +106,126 @@ impl IpcHandler {
                 self.manager.invoke_action(id, &action).await;
                 IpcResponse::success()
             }
+            IpcCommand::TestRules {
+                app_name,
+                summary,
+                body,
+                urgency,
+                hints,
+                actions,
+            } => self.test_rules(app_name, summary, body, urgency, hints, actions),
+            IpcCommand::ListRules => {
+                let rules: Vec<_> = self
+                    .manager
+                    .list_rules()
+                    .into_iter()
+                    .map(|(index, name, enabled)| {
+                        serde_json::json!({
+                            "index": index,
+                            "name": name,
+                            "enabled": enabled,
+                        })
+                    })
+                    .collect();
+                IpcResponse::with_data(rules)
+            }
+            IpcCommand::EnableRule { name } => self.set_rule_enabled(&name, true),
+            IpcCommand::DisableRule { name } => self.set_rule_enabled(&name, false),
+            IpcCommand::GetRateLimits => IpcResponse::with_data(self.manager.throttle_status()),
+            // The server streams the events once this is acknowledged
+            IpcCommand::Subscribe => IpcResponse::success(),
+        }
+    }
+
+    /// Toggle named rules, failing if the name is unknown
+    fn set_rule_enabled(&self, name: &str, enabled: bool) -> IpcResponse {
+        if self.manager.set_rule_enabled(name, enabled) {
+            IpcResponse::success()
+        } else {
+            IpcResponse::error(format!("No rule named '{}'", name))
         }
     }
+
+    /// Dry-run the rule engine and report matches and effective settings
+    fn test_rules(
+        &self,
+        app_name: String,
+        summary: String,
+        body: String,
+        urgency: Option<String>,
+        hints: HashMap<String, String>,
+        actions: Vec<String>,
+    ) -> IpcResponse {
+        let mut parsed_hints = NotificationHints::default();
+        for (key, value) in &hints {
+            if !parsed_hints.set(key, value) {
+                return IpcResponse::error(format!("Invalid value for hint '{}': {}", key, value));
+            }
+        }
+        if let Some(ref urgency) = urgency {
+            if !parsed_hints.set("urgency", urgency) {
+                return IpcResponse::error(format!("Invalid urgency: {}", urgency));
+            }
+        }
+
+        // Offer each action key with itself as the label
+        let actions = actions.iter().flat_map(|key| [key.clone(), key.clone()]);
+
+        let mut notification = Notification::new(
+            0,
+            app_name,
+            0,
+            String::new(),
+            summary,
+            body,
+            actions.collect(),
+            parsed_hints,
+            -1,
+        );
+
+        let evaluation = self.manager.apply_rules(&mut notification);
+        self.manager.apply_script(&mut notification);
+        // These read the config themselves, so ask before holding it
+        let timeout = self.manager.calculate_timeout(&notification);
+        let escalate = self.manager.escalation_policy(&notification);
+        let config = self.manager.config();
+        let config = config.read();
+
+        let matched: Vec<_> = evaluation
+            .matched
+            .iter()
+            .map(|&index| {
+                let rule = config.rules.get(index);
+                serde_json::json!({
+                    "index": index,
+                    "name": rule.and_then(|rule| rule.name.as_ref()),
+                    "criteria": rule.map(|rule| &rule.criteria),
+                })
+            })
+            .collect();
+
+        IpcResponse::with_data(serde_json::json!({
+            "matched": matched,
+            "timeout": timeout,
+            "urgency": notification.hints.urgency.to_string(),
+            "anchor": notification.anchor.unwrap_or(config.positioning.anchor),
+            "display": notification.display,
+            "history": notification.should_persist(),
+            "sound": !notification.skip_sound && !notification.hints.suppress_sound,
+            "css_class": notification.css_class,
+            "icon": notification.icon,
+            "app_name": notification.display_app_name(),
+            "summary": notification.summary,
+            "body": notification.body,
+            "sound_file": notification.sound,
+            "group": notification.group_key(),
+            "max_visible_per_app": notification
+                .max_visible_per_app
+                .unwrap_or(config.general.max_visible_per_app),
+            "escalate": escalate,
+            "exec": evaluation.actions.exec,
+            "invoke_action": evaluation.actions.invoke_action,
+            "invoke_delay": evaluation.actions.invoke_delay,
+        }))
+    }
 }
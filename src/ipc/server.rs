@@ -3,14 +3,16 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_channel::Sender;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
 use tracing::{debug, error, info, warn};
 
 use crate::dnd::DndState;
-use crate::notification::{NotificationManager, UiEvent};
+use crate::notification::{NotificationEvent, NotificationManager, UiEvent};
 
-use super::commands::IpcCommand;
+use super::commands::{IpcCommand, IpcResponse};
 use super::handler::IpcHandler;
 
 /// Get the default socket path
@@ -79,10 +81,17 @@ async fn handle_client(stream: UnixStream, handler: Arc<IpcHandler>) -> Result<(
         debug!("Received IPC command: {}", line.trim());
 
         let response = match serde_json::from_str::<IpcCommand>(&line) {
+            Ok(IpcCommand::Subscribe) => {
+                // Subscribe before acknowledging so no event slips through
+                let events = handler.subscribe();
+                let response = serde_json::to_string(&IpcResponse::success())? + "\n";
+                writer.write_all(response.as_bytes()).await?;
+                return stream_events(&mut writer, events).await;
+            }
             Ok(command) => handler.handle(command).await,
             Err(e) => {
                 warn!("Invalid IPC command: {}", e);
-                super::commands::IpcResponse::error(format!("Invalid command: {}", e))
+                IpcResponse::error(format!("Invalid command: {}", e))
             }
         };
 
@@ -94,3 +103,27 @@ async fn handle_client(stream: UnixStream, handler: Arc<IpcHandler>) -> Result<(
 
     Ok(())
 }
+
+/// Write each event as a JSON line until the client goes away
+async fn stream_events(
+    writer: &mut (impl AsyncWrite + Unpin),
+    mut events: broadcast::Receiver<NotificationEvent>,
+) -> Result<()> {
+    debug!("IPC client subscribed to events");
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(missed)) => {
+                warn!("IPC subscriber missed {} notification events", missed);
+                continue;
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        };
+
+        let line = event.to_json().to_string() + "\n";
+        if writer.write_all(line.as_bytes()).await.is_err() {
+            debug!("IPC subscriber disconnected");
+            return Ok(());
+        }
+    }
+}
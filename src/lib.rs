@@ -5,11 +5,13 @@ pub mod config;
 pub mod dbus;
 pub mod dnd;
 pub mod history;
+pub mod idle;
 pub mod ipc;
 pub mod mpris;
 pub mod notification;
 pub mod positioning;
 pub mod rules;
+pub mod scripting;
 pub mod ui;
 
 #[cfg(feature = "sound")]
@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -13,11 +14,13 @@ mod config;
 mod dbus;
 mod dnd;
 mod history;
+mod idle;
 mod ipc;
 mod mpris;
 mod notification;
 mod positioning;
 mod rules;
+mod scripting;
 mod ui;
 
 #[cfg(feature = "sound")]
@@ -25,11 +28,12 @@ mod sound;
 
 use compositor::CompositorIpc;
 use config::ConfigLoader;
-use dbus::start_dbus_server_with_history;
+use dbus::start_dbus_server;
 use dnd::DndState;
-use history::HistoryStore;
+use history::{HistoryStore, HistoryWriter};
+use idle::IdleMonitor;
 use ipc::start_ipc_server_with_ui;
-use notification::{ActionEvent, NotificationManager, UiEvent};
+use notification::{ActionEvent, NotificationManager, Snapshot, UiEvent};
 use ui::SwaynotiApp;
 
 /// Swaynoti - A modern Wayland notification daemon
@@ -69,7 +73,7 @@ fn main() -> Result<()> {
     info!("Starting swaynoti v{}", env!("CARGO_PKG_VERSION"));
 
     // Load configuration
-    let config = if let Some(ref path) = args.config {
+    let (config, rules) = if let Some(ref path) = args.config {
         ConfigLoader::load_from_path(&path.into())?
     } else {
         ConfigLoader::load()?
@@ -91,44 +95,44 @@ fn main() -> Result<()> {
     // Create communication channels
     let (ui_sender, ui_receiver) = async_channel::unbounded::<UiEvent>();
     let (action_sender, action_receiver) = async_channel::unbounded::<ActionEvent>();
-    // Create a second receiver for D-Bus signal emission
-    let (action_dbus_sender, action_dbus_receiver) = async_channel::unbounded::<ActionEvent>();
-    let (close_sender, close_receiver) =
-        async_channel::unbounded::<(u32, notification::CloseReason)>();
 
     // Create DND state
     let dnd_state = Arc::new(DndState::new());
 
     // Create notification manager
-    let manager = Arc::new(NotificationManager::new(
-        config.clone(),
-        ui_sender.clone(),
-        close_sender,
-    ));
+    let mut manager = NotificationManager::new(config.clone(), rules, ui_sender.clone())
+        .with_action_sender(action_sender.clone())
+        .with_config_path(args.config.as_ref().map(PathBuf::from))
+        .with_dnd(dnd_state.clone());
+    if let Some(ref store) = history_store {
+        manager = manager.with_history(store.clone());
+    }
+    if let Some(snapshot) = Snapshot::default_location() {
+        manager = manager.with_snapshot(snapshot);
+    }
+    let manager = Arc::new(manager);
+
+    // Subscribe the UI before anything can happen
+    let ui_events = manager.subscribe_lossless();
 
     // Start the tokio runtime for async tasks
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?;
 
+    // Bring back what was on screen before a crash or restart
+    runtime.block_on(manager.restore_snapshot());
+
     // Clone for async tasks
     let dbus_manager = manager.clone();
-    let dbus_history = history_store.clone();
     let ipc_manager = manager.clone();
     let ipc_dnd = dnd_state.clone();
     let ipc_config = config.clone();
 
     // Spawn async tasks
     runtime.spawn(async move {
-        // Start D-Bus server with history
-        match start_dbus_server_with_history(
-            dbus_manager,
-            close_receiver,
-            action_dbus_receiver,
-            dbus_history,
-        )
-        .await
-        {
+        // Start D-Bus server
+        match start_dbus_server(dbus_manager).await {
             Ok(_connection) => {
                 // Keep the connection alive forever
                 // The connection must stay in scope for the D-Bus name to remain registered
@@ -153,6 +157,52 @@ fn main() -> Result<()> {
         }
     });
 
+    // Record in history how notifications were closed
+    if let Some(ref store) = history_store {
+        let writer = HistoryWriter::new(store.clone(), manager.clone());
+        runtime.spawn(writer.run());
+    }
+
+    // Write the active notifications to disk as they change
+    let snapshot_manager = manager.clone();
+    runtime.spawn(async move {
+        snapshot_manager.run_snapshots().await;
+    });
+
+    // Close notifications when their timers run out
+    let expiry_manager = manager.clone();
+    runtime.spawn(async move {
+        expiry_manager.run_expirations().await;
+    });
+
+    // Re-alert for notifications nobody reacted to
+    let escalation_manager = manager.clone();
+    runtime.spawn(async move {
+        escalation_manager.run_escalations().await;
+    });
+
+    // Freeze expiration timers while the user is away
+    if let Some(threshold) = config.read().general.idle_threshold {
+        match IdleMonitor::new(std::time::Duration::from_secs(threshold)).spawn() {
+            Ok(idle_receiver) => {
+                let idle_manager = manager.clone();
+                runtime.spawn(async move {
+                    while let Ok(idle) = idle_receiver.recv().await {
+                        idle_manager.set_idle(idle);
+                    }
+                });
+            }
+            Err(e) => tracing::warn!("Idle-aware timeouts disabled: {:#}", e),
+        }
+    }
+
+    // Summarize suppressed notifications when DND ends
+    let dnd_manager = manager.clone();
+    let dnd_changes = dnd_state.subscribe();
+    runtime.spawn(async move {
+        dnd_manager.watch_dnd(dnd_changes).await;
+    });
+
     // Start DND scheduler
     let dnd_config = config.read().dnd.clone();
     let scheduler_dnd = dnd_state.clone();
@@ -163,26 +213,17 @@ fn main() -> Result<()> {
 
     // Handle action events from UI
     let action_manager = manager.clone();
-    let action_history = history_store.clone();
-    let dbus_sender = action_dbus_sender.clone();
     runtime.spawn(async move {
         while let Ok(event) = action_receiver.recv().await {
             match &event {
                 ActionEvent::ActionInvoked { id, action_key } => {
                     info!("Action '{}' invoked on notification {}", action_key, id);
                     action_manager.invoke_action(*id, action_key).await;
-                    // Forward to D-Bus server for signal emission
-                    let _ = dbus_sender.send(event.clone()).await;
-                    // Close the notification after action is invoked
-                    action_manager
-                        .close_notification(*id, notification::CloseReason::Dismissed)
-                        .await;
+                    // Close the notification after action is invoked, unless resident
+                    action_manager.close_after_action(*id).await;
                 }
                 ActionEvent::Dismissed { id } => {
                     info!("Notification {} dismissed by user", id);
-                    if let Some(ref store) = action_history {
-                        let _ = store.mark_dismissed(*id);
-                    }
                     action_manager
                         .close_notification(*id, notification::CloseReason::Dismissed)
                         .await;
@@ -203,29 +244,12 @@ fn main() -> Result<()> {
                 }
                 ActionEvent::InlineReply { id, text } => {
                     info!("Inline reply for notification {}: {}", id, text);
-                    // Send the reply as an action with the text and forward to D-Bus
-                    let inline_action = ActionEvent::ActionInvoked {
-                        id: *id,
-                        action_key: format!("inline-reply:{}", text),
-                    };
-                    action_manager
-                        .invoke_action(*id, &format!("inline-reply:{}", text))
-                        .await;
-                    let _ = dbus_sender.send(inline_action).await;
-                    action_manager
-                        .close_notification(*id, notification::CloseReason::Dismissed)
-                        .await;
+                    action_manager.reply(*id, text).await;
+                    action_manager.close_after_action(*id).await;
                 }
                 ActionEvent::DefaultAction { id } => {
                     info!("Default action triggered for notification {}", id);
                     action_manager.invoke_action(*id, "default").await;
-                    // Forward to D-Bus server for signal emission
-                    let _ = dbus_sender
-                        .send(ActionEvent::ActionInvoked {
-                            id: *id,
-                            action_key: "default".to_string(),
-                        })
-                        .await;
                 }
             }
         }
@@ -237,11 +261,12 @@ fn main() -> Result<()> {
     gtk4::init().expect("Failed to initialize GTK4");
 
     // Create and run the GTK application
-    let app = SwaynotiApp::new(config, action_sender, history_store);
+    let app = SwaynotiApp::new(config, action_sender, history_store, ui_events);
 
     info!("Starting GTK application");
     app.run(ui_receiver);
 
     info!("Swaynoti shutting down");
+    manager.flush_snapshot();
     Ok(())
 }
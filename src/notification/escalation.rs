@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use async_channel::{Receiver, Sender};
+use parking_lot::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use tracing::debug;
+
+use super::Notification;
+use crate::config::{EscalationAction, EscalationPolicy};
+
+/// An escalation that came due: notification ID and the generation it was
+/// scheduled for
+pub type Due = (u32, u64);
+
+/// Escalation state of one notification
+struct Escalation {
+    policy: EscalationPolicy,
+    /// Escalations done so far
+    rounds: u32,
+    /// Bumped every time the sleeper is started, so stale ones are ignored
+    generation: u64,
+    /// Last state of a notification that expired, to bring it back
+    parked: Option<Notification>,
+    /// Sleeper until the next escalation
+    task: JoinHandle<()>,
+}
+
+impl Drop for Escalation {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// What to do for an escalation that came due
+pub struct Escalate {
+    pub action: EscalationAction,
+    /// Which escalation this is, starting at 1
+    pub round: u32,
+    /// The notification, if it expired in the meantime
+    pub parked: Option<Notification>,
+}
+
+struct EscalationState {
+    escalations: HashMap<u32, Escalation>,
+    next_generation: u64,
+}
+
+/// Re-alerts for notifications nobody reacted to, one per notification ID
+///
+/// An escalation is scheduled when a notification arrives and comes due
+/// every `after` seconds until it ran `times` times or the notification was
+/// acknowledged. A notification that expires keeps its escalation and is
+/// parked here, so it can be shown again. Due escalations are sent on a
+/// channel and confirmed with [`Escalations::due`], like expiries.
+pub struct Escalations {
+    state: Mutex<EscalationState>,
+    due_sender: Sender<Due>,
+}
+
+impl Escalations {
+    pub fn new() -> (Self, Receiver<Due>) {
+        let (due_sender, due_receiver) = async_channel::unbounded();
+        let escalations = Self {
+            state: Mutex::new(EscalationState {
+                escalations: HashMap::new(),
+                next_generation: 0,
+            }),
+            due_sender,
+        };
+        (escalations, due_receiver)
+    }
+
+    /// Start escalating a new notification
+    pub fn start(&self, id: u32, policy: EscalationPolicy) {
+        if policy.times == 0 || policy.after == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock();
+        state.next_generation += 1;
+        let generation = state.next_generation;
+        debug!(
+            "Notification {} escalates ({:?}) after {} s, {} times",
+            id, policy.action, policy.after, policy.times
+        );
+
+        let task = self.run(id, generation, policy.after);
+        state.escalations.insert(
+            id,
+            Escalation {
+                policy,
+                rounds: 0,
+                generation,
+                parked: None,
+                task,
+            },
+        );
+    }
+
+    /// Stop escalating a notification that was acknowledged
+    pub fn cancel(&self, id: u32) {
+        if self.state.lock().escalations.remove(&id).is_some() {
+            debug!("Escalation of {} cancelled", id);
+        }
+    }
+
+    /// Keep an expired notification until its next escalation
+    pub fn park(&self, id: u32, notification: Notification) {
+        if let Some(escalation) = self.state.lock().escalations.get_mut(&id) {
+            escalation.parked = Some(notification);
+        }
+    }
+
+    /// The escalation that came due, if it is still current
+    ///
+    /// Follow up with [`Self::advance`] or [`Self::postpone`].
+    pub fn due(&self, (id, generation): Due) -> Option<Escalate> {
+        let state = self.state.lock();
+        let escalation = state.escalations.get(&id)?;
+        if escalation.generation != generation {
+            return None;
+        }
+
+        Some(Escalate {
+            action: escalation.policy.action,
+            round: escalation.rounds + 1,
+            parked: escalation.parked.clone(),
+        })
+    }
+
+    /// Count a due escalation as done and schedule the next one, if any
+    pub fn advance(&self, id: u32) {
+        let mut state = self.state.lock();
+        let Some(escalation) = state.escalations.get_mut(&id) else {
+            return;
+        };
+
+        escalation.rounds += 1;
+        escalation.parked = None;
+        if escalation.rounds >= escalation.policy.times {
+            debug!("Last escalation of {}", id);
+            state.escalations.remove(&id);
+        } else {
+            self.restart(&mut state, id);
+        }
+    }
+
+    /// Try a due escalation again later without counting it
+    pub fn postpone(&self, id: u32) {
+        let mut state = self.state.lock();
+        if state.escalations.contains_key(&id) {
+            self.restart(&mut state, id);
+        }
+    }
+
+    /// Start a new sleeper for an escalation
+    fn restart(&self, state: &mut EscalationState, id: u32) {
+        state.next_generation += 1;
+        let generation = state.next_generation;
+        if let Some(escalation) = state.escalations.get_mut(&id) {
+            escalation.generation = generation;
+            escalation.task = self.run(id, generation, escalation.policy.after);
+        }
+    }
+
+    /// Spawn the sleeper for the next escalation
+    fn run(&self, id: u32, generation: u64, after: u64) -> JoinHandle<()> {
+        let sender = self.due_sender.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_secs(after)).await;
+            let _ = sender.send((id, generation)).await;
+        })
+    }
+}
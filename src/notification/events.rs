@@ -0,0 +1,98 @@
+use serde_json::json;
+
+use super::{CloseReason, Notification};
+use crate::config::EscalationAction;
+
+/// Lifecycle event broadcast by the [`NotificationManager`]
+///
+/// Subscribe with [`NotificationManager::subscribe`]; slow subscribers may
+/// miss events and are told how many through `RecvError::Lagged`. Those that
+/// must see every event use [`NotificationManager::subscribe_lossless`].
+///
+/// [`NotificationManager`]: super::NotificationManager
+/// [`NotificationManager::subscribe`]: super::NotificationManager::subscribe
+/// [`NotificationManager::subscribe_lossless`]: super::NotificationManager::subscribe_lossless
+#[derive(Debug, Clone)]
+pub enum NotificationEvent {
+    /// A new notification was stored, shown now or queued
+    Added(Notification),
+    /// A stored notification was replaced or repeated
+    Updated(Notification),
+    /// A notification got a popup, right away or after waiting in the queue
+    Shown(Notification),
+    /// A notification was closed; carries its last state
+    Closed {
+        notification: Notification,
+        reason: CloseReason,
+    },
+    /// An action was invoked, by the user or by a rule
+    ActionInvoked { id: u32, action_key: String },
+    /// The user sent an inline reply
+    Replied { id: u32, text: String },
+    /// Do Not Disturb was turned on or off
+    DndChanged(bool),
+    /// History entries were added or changed
+    HistoryChanged,
+    /// A notification nobody reacted to was brought back to attention
+    Escalated {
+        notification: Notification,
+        action: EscalationAction,
+        round: u32,
+    },
+}
+
+impl NotificationEvent {
+    /// Compact JSON form sent to IPC subscribers
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Added(n) | Self::Updated(n) | Self::Shown(n) => json!({
+                "event": match self {
+                    Self::Added(_) => "added",
+                    Self::Updated(_) => "updated",
+                    _ => "shown",
+                },
+                "id": n.id,
+                "app": n.app_name,
+                "summary": n.summary,
+                "body": n.body,
+                "urgency": n.hints.urgency.to_string(),
+            }),
+            Self::Closed {
+                notification,
+                reason,
+            } => json!({
+                "event": "closed",
+                "id": notification.id,
+                "app": notification.app_name,
+                "reason": reason,
+            }),
+            Self::ActionInvoked { id, action_key } => json!({
+                "event": "action_invoked",
+                "id": id,
+                "action": action_key,
+            }),
+            Self::Replied { id, text } => json!({
+                "event": "replied",
+                "id": id,
+                "text": text,
+            }),
+            Self::DndChanged(enabled) => json!({
+                "event": "dnd_changed",
+                "enabled": enabled,
+            }),
+            Self::HistoryChanged => json!({ "event": "history_changed" }),
+            Self::Escalated {
+                notification,
+                action,
+                round,
+            } => json!({
+                "event": "escalated",
+                "id": notification.id,
+                "app": notification.app_name,
+                "summary": notification.summary,
+                "action": action,
+                "round": round,
+            }),
+        }
+    }
+}
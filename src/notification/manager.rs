@@ -1,15 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
-use async_channel::Sender;
+use async_channel::{Receiver, Sender};
 use chrono::Utc;
 use parking_lot::RwLock;
+use serde::Serialize;
+use tokio::sync::{broadcast, watch};
 use tokio::time::{sleep, Duration};
-use tracing::{debug, info};
+use tracing::{debug, error, info};
 
+use super::escalation::{Due, Escalate, Escalations};
+use super::events::NotificationEvent;
+use super::rate_limit::{RateLimiter, Throttle, ThrottleStatus};
+use super::snapshot::Snapshot;
+use super::timer::{Expiry, ExpiryTimers};
 use super::{Notification, Urgency};
-use crate::config::Config;
+use crate::config::ScriptConfig;
+use crate::config::{
+    Anchor, Config, ConfigLoader, EscalationAction, EscalationPolicy, PerAppOverflow, TimeoutConfig,
+};
+use crate::dnd::DndState;
+use crate::history::{HistoryEntry, HistoryStore};
+use crate::rules::{RuleEvaluation, RuleSet};
+#[cfg(feature = "scripting")]
+use crate::scripting::ScriptFilter;
 
 /// Events sent to the UI thread
 #[derive(Debug, Clone)]
@@ -20,8 +36,12 @@ pub enum UiEvent {
     Update(u32, Notification),
     /// Close a notification
     Close(u32),
-    /// Reposition all notifications
-    Reposition,
+    /// Remove the popup of a notification that went back to the pending queue
+    Hide(u32),
+    /// Restack the popups in this display order
+    Reposition(Vec<u32>),
+    /// Number of notifications waiting for a free popup slot
+    Pending(usize),
     /// Show notification center
     ShowCenter,
     /// Hide notification center
@@ -50,7 +70,8 @@ pub enum ActionEvent {
 }
 
 /// Reason for closing a notification (FreeDesktop spec)
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
 #[repr(u32)]
 pub enum CloseReason {
     Expired = 1,
@@ -59,7 +80,16 @@ pub enum CloseReason {
     Undefined = 4,
 }
 
+/// Notification IDs reserved in the history database at a time
+const ID_BLOCK: u32 = 1000;
+
+/// Events kept for subscribers that fall behind
+const EVENT_CAPACITY: usize = 256;
+
 /// Manages active notifications and their lifecycle
+///
+/// Locks are taken in the order `config`, `display_order`, `notifications`,
+/// `visible`, and never one while holding a later one.
 pub struct NotificationManager {
     /// Active notifications by ID
     notifications: RwLock<HashMap<u32, Notification>>,
@@ -67,181 +97,1043 @@ pub struct NotificationManager {
     /// Display order of notification IDs
     display_order: RwLock<Vec<u32>>,
 
+    /// Notifications that currently have a popup; the rest are pending
+    visible: RwLock<HashSet<u32>>,
+
     /// Next notification ID
     next_id: AtomicU32,
 
+    /// IDs below this are already recorded in the history database
+    reserved_ids: AtomicU32,
+
     /// Configuration
     config: Arc<RwLock<Config>>,
 
+    /// Rules compiled from the configuration
+    rules: RwLock<RuleSet>,
+
+    /// Filter script from `[script]`
+    #[cfg(feature = "scripting")]
+    script: RwLock<Option<ScriptFilter>>,
+
+    /// Expiration timers of the active notifications
+    timers: ExpiryTimers,
+
+    /// Timers that ran out, consumed by [`Self::run_expirations`]
+    expired_receiver: Receiver<Expiry>,
+
+    /// Re-alerts for notifications nobody reacted to
+    escalations: Escalations,
+
+    /// Escalations that came due, consumed by [`Self::run_escalations`]
+    escalation_receiver: Receiver<Due>,
+
     /// Channel to send UI events
     ui_sender: Sender<UiEvent>,
 
-    /// Channel to receive close signals (for D-Bus)
-    close_sender: Sender<(u32, CloseReason)>,
+    /// Lifecycle events for every interested subsystem
+    events: broadcast::Sender<NotificationEvent>,
+
+    /// Subscribers that must see every event, however far behind they are
+    lossless_subscribers: RwLock<Vec<Sender<NotificationEvent>>>,
+
+    /// Channel for actions invoked by the daemon itself (rule auto-invoke)
+    action_sender: Option<Sender<ActionEvent>>,
+
+    /// Config file given on the command line, reloaded instead of the default
+    config_path: Option<PathBuf>,
+
+    /// Do Not Disturb state shared with IPC and the scheduler
+    dnd: Option<Arc<DndState>>,
+
+    /// Notifications kept from popping up during the current DND period
+    dnd_suppressed: AtomicU32,
+
+    /// Per-app token buckets from `[rate_limit]`
+    rate_limiter: RateLimiter,
+
+    /// Where active notifications are saved to survive a restart
+    snapshot: Option<Snapshot>,
+
+    /// History database, which also keeps the ID counter across restarts
+    history: Option<Arc<HistoryStore>>,
 }
 
 impl NotificationManager {
-    pub fn new(
-        config: Arc<RwLock<Config>>,
-        ui_sender: Sender<UiEvent>,
-        close_sender: Sender<(u32, CloseReason)>,
-    ) -> Self {
+    /// Create the manager with the rules compiled when `config` was loaded
+    pub fn new(config: Arc<RwLock<Config>>, rules: RuleSet, ui_sender: Sender<UiEvent>) -> Self {
+        let script = Self::load_script(&config.read().script).unwrap_or_else(|e| {
+            error!("Ignoring notification script: {}", e);
+            None
+        });
+        #[cfg(not(feature = "scripting"))]
+        let _ = script;
+        let (timers, expired_receiver) = ExpiryTimers::new();
+        let (escalations, escalation_receiver) = Escalations::new();
+
         Self {
             notifications: RwLock::new(HashMap::new()),
             display_order: RwLock::new(Vec::new()),
+            visible: RwLock::new(HashSet::new()),
             next_id: AtomicU32::new(1),
+            reserved_ids: AtomicU32::new(0),
             config,
+            rules: RwLock::new(rules),
+            #[cfg(feature = "scripting")]
+            script: RwLock::new(script),
+            timers,
+            expired_receiver,
+            escalations,
+            escalation_receiver,
             ui_sender,
-            close_sender,
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            lossless_subscribers: RwLock::new(Vec::new()),
+            action_sender: None,
+            config_path: None,
+            dnd: None,
+            dnd_suppressed: AtomicU32::new(0),
+            rate_limiter: RateLimiter::default(),
+            snapshot: None,
+            history: None,
+        }
+    }
+
+    /// Record notifications in history and continue notification IDs where
+    /// the last run left off
+    ///
+    /// IDs are reserved in the history database in blocks, so apps holding
+    /// IDs from before a restart can't replace new notifications. A restart
+    /// skips the rest of the current block.
+    pub fn with_history(mut self, history: Arc<HistoryStore>) -> Self {
+        match history.last_notification_id() {
+            Ok(last) => {
+                self.next_id = AtomicU32::new(last.saturating_add(1).max(1));
+                debug!("Continuing notification IDs after {}", last);
+            }
+            Err(e) => error!("Failed to read the last notification ID: {}", e),
         }
+        self.history = Some(history);
+        self
+    }
+
+    /// Save active notifications here and restore them on startup
+    pub fn with_snapshot(mut self, snapshot: Snapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    /// Suppress popups while Do Not Disturb is on
+    pub fn with_dnd(mut self, dnd: Arc<DndState>) -> Self {
+        self.dnd = Some(dnd);
+        self
+    }
+
+    /// Reload from this config file instead of the default location
+    pub fn with_config_path(mut self, path: Option<PathBuf>) -> Self {
+        self.config_path = path;
+        self
+    }
+
+    /// Route daemon-invoked actions through the same channel as the UI
+    pub fn with_action_sender(mut self, action_sender: Sender<ActionEvent>) -> Self {
+        self.action_sender = Some(action_sender);
+        self
+    }
+
+    /// Receive lifecycle events from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<NotificationEvent> {
+        self.events.subscribe()
+    }
+
+    /// Receive every lifecycle event from now on, without a limit on how
+    /// many can queue up
+    ///
+    /// For subscribers whose work must not be skipped, like marking history.
+    pub fn subscribe_lossless(&self) -> Receiver<NotificationEvent> {
+        let (sender, receiver) = async_channel::unbounded();
+        self.lossless_subscribers.write().push(sender);
+        receiver
+    }
+
+    /// Send an event to all subscribers, if there are any
+    fn emit(&self, event: NotificationEvent) {
+        self.lossless_subscribers
+            .write()
+            .retain(|sender| sender.try_send(event.clone()).is_ok());
+        let _ = self.events.send(event);
+    }
+
+    /// Tell subscribers that history entries were added or changed
+    pub fn history_changed(&self) {
+        self.emit(NotificationEvent::HistoryChanged);
     }
 
     /// Generate a new unique notification ID
     fn generate_id(&self) -> u32 {
-        self.next_id.fetch_add(1, Ordering::SeqCst)
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        if let Some(ref history) = self.history {
+            // Only touch the database once per block of IDs
+            if id >= self.reserved_ids.load(Ordering::SeqCst) {
+                let reserved = id.saturating_add(ID_BLOCK);
+                match history.set_last_notification_id(reserved - 1) {
+                    Ok(()) => {
+                        self.reserved_ids.fetch_max(reserved, Ordering::SeqCst);
+                    }
+                    Err(e) => error!("Failed to reserve notification IDs: {}", e),
+                }
+            }
+        }
+        id
+    }
+
+    /// Apply all matching `[[rules]]` entries to a notification
+    ///
+    /// Actions of later rules override earlier ones. Returns the evaluation
+    /// so side effects that need the assigned ID (like `exec`) can run once
+    /// the notification is stored, and so dry runs can report matches.
+    pub fn apply_rules(&self, notification: &mut Notification) -> RuleEvaluation {
+        let evaluation = self.rules.read().evaluate(notification);
+        evaluation.apply(notification);
+        evaluation
+    }
+
+    /// Load the filter script configured in `[script]`
+    #[cfg(feature = "scripting")]
+    fn load_script(config: &ScriptConfig) -> anyhow::Result<Option<ScriptFilter>> {
+        Ok(ScriptFilter::load(config)?)
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    fn load_script(config: &ScriptConfig) -> anyhow::Result<Option<()>> {
+        if config.path.is_some() {
+            tracing::warn!(
+                "A notification script is configured but scripting support is not compiled in"
+            );
+        }
+        Ok(None)
+    }
+
+    /// Run the filter script on a notification, after the rules
+    pub fn apply_script(&self, notification: &mut Notification) {
+        #[cfg(feature = "scripting")]
+        if let Some(ref script) = *self.script.read() {
+            script.apply(notification);
+        }
+
+        #[cfg(not(feature = "scripting"))]
+        let _ = notification;
+    }
+
+    /// Reload the config file and recompile the rules
+    ///
+    /// Rules enabled or disabled at runtime keep their state.
+    pub fn reload_config(&self) -> anyhow::Result<()> {
+        let (config, rules) = match self.config_path {
+            Some(ref path) => ConfigLoader::load_from_path(path)?,
+            None => ConfigLoader::load()?,
+        };
+        let script = Self::load_script(&config.script)?;
+
+        #[cfg(feature = "scripting")]
+        {
+            *self.script.write() = script;
+        }
+        #[cfg(not(feature = "scripting"))]
+        let _ = script;
+
+        let mut current = self.rules.write();
+        *current = rules.with_state_from(&current);
+        *self.config.write() = config;
+
+        info!("Configuration reloaded ({} rules)", current.len());
+        Ok(())
+    }
+
+    /// Enable or disable the rules with the given name
+    ///
+    /// Returns `false` if no rule has that name.
+    pub fn set_rule_enabled(&self, name: &str, enabled: bool) -> bool {
+        let found = self.rules.write().set_enabled(name, enabled);
+        if found {
+            info!(
+                "Rule '{}' {}",
+                name,
+                if enabled { "enabled" } else { "disabled" }
+            );
+        }
+        found
+    }
+
+    /// List the configured rules with their runtime state
+    pub fn list_rules(&self) -> Vec<(usize, Option<String>, bool)> {
+        let rules = self.rules.read();
+        rules
+            .rules()
+            .iter()
+            .map(|rule| (rule.index, rule.name.clone(), rules.is_enabled(rule)))
+            .collect()
+    }
+
+    /// Drop a notification if its app is over the `[rate_limit]`
+    ///
+    /// Dropped notifications are counted in a single popup from the app that
+    /// is updated in place while the flood lasts. Returns an ID to hand back
+    /// to the sender, or `None` if the notification should go through.
+    /// Replacements of active notifications are never throttled, and neither
+    /// are repeats, which only bump the existing popup's counter.
+    pub async fn throttle(&self, notification: &Notification) -> Option<u32> {
+        if notification.replaces_id > 0
+            && self
+                .notifications
+                .read()
+                .contains_key(&notification.replaces_id)
+        {
+            return None;
+        }
+        if notification.replaces_id == 0
+            && notification.should_display()
+            && self.find_duplicate(notification).is_some()
+        {
+            return None;
+        }
+
+        let throttle = {
+            let config = self.config.read();
+            if !config.rate_limit.enabled {
+                return None;
+            }
+            self.rate_limiter
+                .check(&notification.app_name, &config.rate_limit, |id| {
+                    self.notifications.read().contains_key(&id)
+                })
+        };
+
+        let Throttle::Dropped {
+            dropped,
+            summary_id,
+        } = throttle
+        else {
+            return None;
+        };
+        debug!(
+            "Notification from '{}' dropped by rate limit ({} so far)",
+            notification.app_name, dropped
+        );
+
+        let mut summary = Notification::new(
+            0,
+            notification.app_name.clone(),
+            summary_id.unwrap_or(0),
+            notification.app_icon.clone(),
+            format!(
+                "{} sent {} more notification{}",
+                notification.display_app_name(),
+                dropped,
+                if dropped == 1 { "" } else { "s" }
+            ),
+            String::new(),
+            Vec::new(),
+            Default::default(),
+            -1,
+        );
+        summary.hints.urgency = Urgency::Low;
+        summary.display_name = notification.display_name.clone();
+        summary.skip_history = true;
+        summary.skip_sound = true;
+
+        let id = self.add_notification(summary).await;
+        if summary_id.is_none() {
+            info!(
+                "Rate limiting notifications from '{}'",
+                notification.app_name
+            );
+            self.rate_limiter.set_summary_id(&notification.app_name, id);
+        }
+
+        // The sender must not be able to close or replace the summary
+        Some(self.generate_id())
+    }
+
+    /// Apps that have been rate limited since startup
+    pub fn throttle_status(&self) -> Vec<ThrottleStatus> {
+        self.rate_limiter.status()
+    }
+
+    /// Find an active notification identical to this one that is recent
+    /// enough to be counted as a repeat
+    ///
+    /// Returns its ID and repeat count.
+    fn find_duplicate(&self, notification: &Notification) -> Option<(u32, u32)> {
+        let window = self.config.read().general.duplicate_window;
+        if window == 0 {
+            return None;
+        }
+        let cutoff = Utc::now() - chrono::Duration::seconds(window as i64);
+
+        self.notifications
+            .read()
+            .values()
+            .find(|existing| {
+                existing.app_name == notification.app_name
+                    && existing.summary == notification.summary
+                    && existing.body == notification.body
+                    && existing.created_at >= cutoff
+            })
+            .map(|existing| (existing.id, existing.repeat_count))
     }
 
     /// Add a new notification or replace an existing one
     pub async fn add_notification(&self, mut notification: Notification) -> u32 {
-        let id = if notification.replaces_id > 0 {
-            // Check if the notification to replace exists
-            let exists = {
-                let notifications = self.notifications.read();
-                notifications.contains_key(&notification.replaces_id)
-            };
+        // A repeat updates the existing popup instead of stacking another one
+        if notification.replaces_id == 0 && notification.should_display() {
+            if let Some((id, count)) = self.find_duplicate(&notification) {
+                debug!(
+                    "Notification from '{}' repeats {} ({} times)",
+                    notification.app_name,
+                    id,
+                    count + 1
+                );
+                notification.replaces_id = id;
+                notification.repeat_count = count + 1;
+            }
+        }
+
+        // Check if the notification to replace exists
+        let replaces_existing = notification.replaces_id > 0
+            && self
+                .notifications
+                .read()
+                .contains_key(&notification.replaces_id);
+
+        // Popups already on screen keep updating during DND
+        let hidden_by_rule = !notification.should_display();
+        let suppressed =
+            !hidden_by_rule && !replaces_existing && self.suppressed_by_dnd(&notification);
 
-            if exists {
+        if suppressed {
+            // Nothing it could replace is stored, so it always gets a new ID
+            let id = self.generate_id();
+            notification.id = id;
+            self.record_history(&mut notification);
+            // The summary points to history, which transient ones skip
+            if notification.should_persist() {
+                self.dnd_suppressed.fetch_add(1, Ordering::SeqCst);
+            }
+            debug!("Notification {} suppressed by Do Not Disturb", id);
+            return id;
+        }
+
+        if hidden_by_rule {
+            // Hand out an ID but never store or show it
+            let id = if replaces_existing {
+                // The update is hidden, so the outdated popup must not stay
+                self.close_notification(notification.replaces_id, CloseReason::Undefined)
+                    .await;
                 notification.replaces_id
             } else {
                 self.generate_id()
-            }
+            };
+            notification.id = id;
+            self.record_history(&mut notification);
+            debug!(
+                "Notification {} from '{}' not displayed ({:?})",
+                id, notification.app_name, notification.display
+            );
+            return id;
+        }
+
+        let id = if replaces_existing {
+            notification.replaces_id
         } else {
             self.generate_id()
         };
 
         notification.id = id;
 
-        // Calculate expiration time
-        let timeout = self.calculate_timeout(&notification);
-        if timeout > 0 {
-            notification.expires_at =
-                Some(Utc::now() + chrono::Duration::milliseconds(timeout as i64));
+        // A repeat updates the history row of the original. The row is
+        // written before the notification is stored, so a repeat that
+        // arrives right after it always finds the row.
+        if notification.repeat_count > 1 {
+            notification.history_row = self.history_row(id);
         }
+        self.record_history(&mut notification);
 
         let is_replacement = {
             let mut notifications = self.notifications.write();
-            let is_replacement = notifications.contains_key(&id);
+            // The popup stays in place, so a pointer over it still counts
+            let is_replacement = match notifications.get(&id) {
+                Some(existing) => {
+                    notification.is_hovered = existing.is_hovered;
+                    true
+                }
+                None => false,
+            };
             notifications.insert(id, notification.clone());
             is_replacement
         };
 
         if is_replacement {
             debug!("Replacing notification {}", id);
+            self.emit(NotificationEvent::Updated(notification.clone()));
             let _ = self
                 .ui_sender
                 .send(UiEvent::Update(id, notification.clone()))
                 .await;
+
+            // A popup on screen gets the full timeout again
+            if self.visible.read().contains(&id) {
+                self.start_timer(id);
+            }
+            self.save_snapshot();
         } else {
-            // Add to display order
-            {
+            self.emit(NotificationEvent::Added(notification.clone()));
+            self.insert_in_order(id, notification.hints.urgency);
+
+            // Re-alert if nobody reacts to it
+            if let Some(policy) = self.escalation_policy(&notification) {
+                self.escalations.start(id, policy);
+            }
+
+            info!("Added notification {}: {}", id, notification.summary);
+            self.update_visible().await;
+        }
+
+        id
+    }
+
+    /// Save a notification to history unless it is transient or skipped by
+    /// a rule
+    ///
+    /// A repeat (`history_row` set) updates the row of the notification it
+    /// merged into; anything else gets a row of its own.
+    fn record_history(&self, notification: &mut Notification) {
+        let Some(ref store) = self.history else {
+            return;
+        };
+        if !notification.should_persist() {
+            return;
+        }
+
+        let (summary, body) = notification.history_summary_body();
+        let mut entry = HistoryEntry::new(
+            notification.id,
+            notification.app_name.clone(),
+            summary.to_string(),
+            body.to_string(),
+            if notification.app_icon.is_empty() {
+                None
+            } else {
+                Some(notification.app_icon.clone())
+            },
+            notification.hints.urgency.to_string(),
+            notification
+                .actions
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect(),
+        );
+        entry.repeat_count = notification.repeat_count;
+        entry.row_id = notification.history_row;
+
+        match store.add(&entry) {
+            Ok(row_id) => {
+                notification.history_row = Some(row_id);
+                self.history_changed();
+            }
+            Err(e) => debug!("Failed to save notification to history: {}", e),
+        }
+    }
+
+    /// Add a notification to the display order according to `sort_order`
+    fn insert_in_order(&self, id: u32, urgency: Urgency) {
+        let sort_order = self.config.read().general.sort_order;
+
+        match sort_order {
+            crate::config::SortOrder::NewestFirst => self.display_order.write().insert(0, id),
+            crate::config::SortOrder::OldestFirst => self.display_order.write().push(id),
+            crate::config::SortOrder::UrgencyDescending => {
+                // Look up urgencies before locking the order
+                let urgencies: HashMap<u32, Urgency> = self
+                    .notifications
+                    .read()
+                    .iter()
+                    .map(|(&existing_id, existing)| (existing_id, existing.hints.urgency))
+                    .collect();
+
+                // Insert based on urgency
                 let mut order = self.display_order.write();
+                let pos = order
+                    .iter()
+                    .position(|existing_id| match urgencies.get(existing_id) {
+                        Some(&existing) => (urgency as u8) > (existing as u8),
+                        None => true,
+                    })
+                    .unwrap_or(order.len());
+                order.insert(pos, id);
+            }
+        }
+    }
+
+    /// Escalation of a notification: its rule's, or the one for its urgency
+    pub fn escalation_policy(&self, notification: &Notification) -> Option<EscalationPolicy> {
+        if let Some(ref policy) = notification.escalation {
+            return Some(policy.clone());
+        }
+
+        let config = self.config.read();
+        match notification.hints.urgency {
+            Urgency::Low => config.escalation.low.clone(),
+            Urgency::Normal => config.escalation.normal.clone(),
+            Urgency::Critical => config.escalation.critical.clone(),
+        }
+    }
+
+    /// Give popups to pending notifications while there is room
+    ///
+    /// Pending notifications are promoted in display order. Popups stack per
+    /// anchor, so every anchor has `max_visible` slots. When every slot of
+    /// its anchor is taken, a critical notification takes over the popup of
+    /// the last visible low-urgency one there, which goes back to the queue. An app at its
+    /// `max_visible_per_app` cap waits in the queue, or with
+    /// `per_app_overflow = "collapse"` swaps its newest notification in for
+    /// its latest popup.
+    async fn update_visible(&self) {
+        let (shown, hidden, visible_order, pending) = {
+            let (max_visible, grouped, max_per_app, per_app_overflow, default_anchor) = {
                 let config = self.config.read();
+                (
+                    config.general.max_visible as usize,
+                    config.general.group_popups,
+                    config.general.max_visible_per_app,
+                    config.general.per_app_overflow,
+                    config.positioning.anchor,
+                )
+            };
+            let order = self.display_order.read();
+            let notifications = self.notifications.read();
+            let mut visible = self.visible.write();
+
+            let anchor_of = |n: &Notification| n.anchor.unwrap_or(default_anchor);
+            // With grouping, popups of the same group and anchor share one stack
+            let stack_of = |id: &u32| notifications.get(id).map(|n| (anchor_of(n), n.group_key()));
+            let stacked_with = |id: u32, visible: &HashSet<u32>| {
+                let stack = stack_of(&id);
+                grouped
+                    && visible
+                        .iter()
+                        .any(|&other| other != id && stack_of(&other) == stack)
+            };
+            let slots_used = |anchor: Anchor, visible: &HashSet<u32>| {
+                let on_anchor = visible
+                    .iter()
+                    .filter_map(|id| notifications.get(id))
+                    .filter(|n| anchor_of(n) == anchor);
+                if grouped {
+                    on_anchor
+                        .map(|n| n.group_key())
+                        .collect::<HashSet<_>>()
+                        .len()
+                } else {
+                    on_anchor.count()
+                }
+            };
 
-                match config.general.sort_order {
-                    crate::config::SortOrder::NewestFirst => order.insert(0, id),
-                    crate::config::SortOrder::OldestFirst => order.push(id),
-                    crate::config::SortOrder::UrgencyDescending => {
-                        // Insert based on urgency
-                        let pos = order
+            let mut shown = Vec::new();
+            let mut hidden = Vec::new();
+            for &id in order.iter() {
+                let Some(notification) = notifications.get(&id) else {
+                    continue;
+                };
+                if visible.contains(&id) {
+                    continue;
+                }
+
+                // Keep one app from taking every slot
+                let app_cap = notification.max_visible_per_app.unwrap_or(max_per_app) as usize;
+                if app_cap > 0 {
+                    let app_visible: Vec<&Notification> = visible
+                        .iter()
+                        .filter_map(|other| notifications.get(other))
+                        .filter(|other| other.app_name == notification.app_name)
+                        .collect();
+                    if app_visible.len() >= app_cap {
+                        // Only a newer notification takes over, so the two
+                        // never swap back and forth
+                        let latest = app_visible
                             .iter()
-                            .position(|&existing_id| {
-                                if let Some(existing) = self.notifications.read().get(&existing_id)
-                                {
-                                    (notification.hints.urgency as u8)
-                                        > (existing.hints.urgency as u8)
-                                } else {
-                                    true
-                                }
+                            .max_by_key(|other| other.created_at)
+                            .filter(|latest| latest.created_at < notification.created_at)
+                            .map(|latest| latest.id);
+                        match (per_app_overflow, latest) {
+                            (PerAppOverflow::Collapse, Some(latest)) => {
+                                debug!(
+                                    "Notification {} replaces {} on screen for '{}'",
+                                    id, latest, notification.app_name
+                                );
+                                send_back(latest, &mut visible, &mut shown, &mut hidden);
+                            }
+                            _ => continue,
+                        }
+                    }
+                }
+
+                let anchor = anchor_of(notification);
+                if !stacked_with(id, &visible) && slots_used(anchor, &visible) >= max_visible {
+                    if notification.hints.urgency != Urgency::Critical {
+                        continue;
+                    }
+                    // Only a popup alone in its stack frees a slot
+                    let victim = order.iter().rev().copied().find(|&other| {
+                        visible.contains(&other)
+                            && !stacked_with(other, &visible)
+                            && notifications.get(&other).is_some_and(|n| {
+                                n.hints.urgency == Urgency::Low && anchor_of(n) == anchor
                             })
-                            .unwrap_or(order.len());
-                        order.insert(pos, id);
+                    });
+                    let Some(victim) = victim else {
+                        continue;
+                    };
+
+                    send_back(victim, &mut visible, &mut shown, &mut hidden);
+                }
+
+                visible.insert(id);
+                shown.push(id);
+            }
+
+            let visible_order: Vec<u32> = order
+                .iter()
+                .copied()
+                .filter(|id| visible.contains(id))
+                .collect();
+            let pending = order.len() - visible_order.len();
+            (shown, hidden, visible_order, pending)
+        };
+
+        for id in hidden {
+            debug!("Notification {} moved back to the pending queue", id);
+            self.timers.cancel(id);
+            let _ = self.ui_sender.send(UiEvent::Hide(id)).await;
+        }
+
+        for id in shown {
+            // Timers only run while the popup can actually be seen
+            self.start_timer(id);
+            if let Some(notification) = self.get_notification(id) {
+                self.emit(NotificationEvent::Shown(notification.clone()));
+                let _ = self.ui_sender.send(UiEvent::Show(notification)).await;
+            }
+        }
+
+        let _ = self
+            .ui_sender
+            .send(UiEvent::Reposition(visible_order))
+            .await;
+        let _ = self.ui_sender.send(UiEvent::Pending(pending)).await;
+
+        self.save_snapshot();
+    }
+
+    /// Queue the active notifications for the snapshot, if enabled
+    ///
+    /// Running timers are saved as `expires_at`. A paused one (hovered, or
+    /// frozen while idle) has no expiry time, so its time left is saved as
+    /// the timeout instead.
+    fn save_snapshot(&self) {
+        let Some(ref snapshot) = self.snapshot else {
+            return;
+        };
+
+        let active: Vec<Notification> = {
+            let order = self.display_order.read();
+            let notifications = self.notifications.read();
+            order
+                .iter()
+                .filter_map(|id| notifications.get(id).cloned())
+                .map(|mut notification| {
+                    if let Some((left, false)) = self.timers.remaining(notification.id) {
+                        notification.expire_timeout =
+                            left.as_millis().clamp(1, i32::MAX as u128) as i32;
                     }
+                    notification
+                })
+                .collect()
+        };
+        snapshot.save(active);
+    }
+
+    /// Write the active notifications to the snapshot file as they change
+    pub async fn run_snapshots(&self) {
+        if let Some(ref snapshot) = self.snapshot {
+            snapshot.run().await;
+        }
+    }
+
+    /// Write a snapshot that is still queued, before shutting down
+    pub fn flush_snapshot(&self) {
+        if let Some(ref snapshot) = self.snapshot {
+            snapshot.flush();
+        }
+    }
+
+    /// Bring back the notifications that were active when the daemon stopped
+    ///
+    /// Timed notifications get the time they had left; those whose timer was
+    /// running and would have expired in the meantime are dropped. Timers
+    /// that were paused don't count the downtime. Escalations of the restored
+    /// notifications start over; notifications that had already expired are
+    /// not in the snapshot, so their escalations end with the restart. Call
+    /// once at startup, before new notifications arrive.
+    pub async fn restore_snapshot(&self) {
+        let Some(ref snapshot) = self.snapshot else {
+            return;
+        };
+        let saved = match snapshot.load() {
+            Ok(saved) => saved,
+            Err(e) => {
+                error!("Failed to restore active notifications: {:#}", e);
+                return;
+            }
+        };
+        if saved.is_empty() {
+            return;
+        }
+
+        let now = Utc::now();
+        let mut restored = Vec::new();
+        {
+            let mut order = self.display_order.write();
+            let mut notifications = self.notifications.write();
+
+            for mut notification in saved {
+                let id = notification.id;
+                // Never hand out a restored ID again
+                self.next_id
+                    .fetch_max(id.saturating_add(1), Ordering::SeqCst);
+
+                if let Some(expires_at) = notification.expires_at.take() {
+                    let remaining = (expires_at - now).num_milliseconds();
+                    if remaining <= 0 {
+                        debug!("Notification {} expired while the daemon was down", id);
+                        continue;
+                    }
+                    notification.expire_timeout = remaining.min(i32::MAX as i64) as i32;
                 }
+
+                // It was announced before the restart
+                notification.skip_sound = true;
+                notification.replaces_id = 0;
+                order.push(id);
+                restored.push(id);
+                notifications.insert(id, notification);
             }
+        }
 
-            info!("Added notification {}: {}", id, notification.summary);
-            let _ = self
-                .ui_sender
-                .send(UiEvent::Show(notification.clone()))
-                .await;
+        for &id in &restored {
+            let policy = self
+                .get_notification(id)
+                .and_then(|notification| self.escalation_policy(&notification));
+            if let Some(policy) = policy {
+                self.escalations.start(id, policy);
+            }
         }
 
-        // Schedule expiration if timeout > 0
+        info!("Restored {} active notifications", restored.len());
+        self.update_visible().await;
+    }
+
+    /// Start (or restart) the expiration timer of a notification
+    fn start_timer(&self, id: u32) {
+        let timeouts = self.config.read().timeouts.clone();
+        let mut notifications = self.notifications.write();
+        let Some(notification) = notifications.get_mut(&id) else {
+            return;
+        };
+
+        let timeout = timeout_for(notification, &timeouts);
         if timeout > 0 {
-            self.schedule_expiration(id, timeout).await;
+            self.timers.start(
+                id,
+                Duration::from_millis(timeout as u64),
+                notification.is_hovered,
+            );
+        } else {
+            self.timers.cancel(id);
         }
+        self.update_expiry(notification);
+    }
 
-        id
+    /// Set when a notification expires from its timer, or clear it while
+    /// the timer is paused
+    fn update_expiry(&self, notification: &mut Notification) {
+        notification.expires_at = match self.timers.remaining(notification.id) {
+            Some((left, true)) => {
+                Some(Utc::now() + chrono::Duration::from_std(left).unwrap_or_default())
+            }
+            _ => None,
+        };
     }
 
     /// Calculate the timeout for a notification
-    fn calculate_timeout(&self, notification: &Notification) -> i32 {
-        let config = self.config.read();
+    pub fn calculate_timeout(&self, notification: &Notification) -> i32 {
+        timeout_for(notification, &self.config.read().timeouts)
+    }
 
-        if notification.expire_timeout == 0 {
-            // Never expires
-            return 0;
+    /// Close notifications as their expiration timers run out
+    ///
+    /// Expiries of timers restarted in the meantime are ignored.
+    pub async fn run_expirations(&self) {
+        while let Ok(expiry) = self.expired_receiver.recv().await {
+            if self.timers.take_expired(expiry) {
+                debug!("Notification {} expired", expiry.0);
+                self.close_notification(expiry.0, CloseReason::Expired)
+                    .await;
+            }
         }
+    }
 
-        if notification.expire_timeout > 0 {
-            // Use provided timeout
-            return notification.expire_timeout;
-        }
+    /// Escalate notifications that were left unacknowledged
+    ///
+    /// An escalation that comes due during Do Not Disturb waits another
+    /// `after` seconds without using up one of its rounds.
+    pub async fn run_escalations(&self) {
+        while let Ok(due) = self.escalation_receiver.recv().await {
+            let Some(escalate) = self.escalations.due(due) else {
+                continue;
+            };
+            let id = due.0;
 
-        // Use server default based on urgency
-        match notification.hints.urgency {
-            Urgency::Low => config.timeouts.low,
-            Urgency::Normal => config.timeouts.normal,
-            Urgency::Critical => config.timeouts.critical,
+            // Wait out Do Not Disturb like new notifications do
+            let suppressed = match escalate.parked {
+                Some(ref notification) => self.suppressed_by_dnd(notification),
+                None => self
+                    .get_notification(id)
+                    .is_some_and(|notification| self.suppressed_by_dnd(&notification)),
+            };
+            if suppressed {
+                debug!("Escalation of {} postponed by Do Not Disturb", id);
+                self.escalations.postpone(id);
+                continue;
+            }
+
+            self.escalations.advance(id);
+            self.escalate(id, escalate).await;
         }
     }
 
-    /// Schedule a notification to expire after the given timeout
-    async fn schedule_expiration(&self, id: u32, timeout_ms: i32) {
-        let ui_sender = self.ui_sender.clone();
-        let close_sender = self.close_sender.clone();
-        let _notifications = Arc::new(&self.notifications);
+    /// Re-alert for a notification nobody reacted to
+    ///
+    /// An expired notification comes back under its own ID, so the app's
+    /// actions still reach it.
+    async fn escalate(&self, id: u32, escalate: Escalate) {
+        let Escalate {
+            action,
+            round,
+            parked,
+        } = escalate;
+        info!(
+            "Escalating notification {} ({:?}, round {})",
+            id, action, round
+        );
 
-        tokio::spawn(async move {
-            sleep(Duration::from_millis(timeout_ms as u64)).await;
+        let notification = match parked {
+            Some(notification) if action == EscalationAction::Sound => {
+                // Nothing comes back on screen, so keep it for the next round
+                self.escalations.park(id, notification.clone());
+                notification
+            }
+            Some(mut notification) => {
+                if action == EscalationAction::Critical {
+                    // Also take the timeout of critical notifications
+                    notification.hints.urgency = Urgency::Critical;
+                    notification.expire_timeout = -1;
+                }
+                notification.expires_at = None;
+                notification.is_hovered = false;
+                self.notifications.write().insert(id, notification.clone());
+                self.insert_in_order(id, notification.hints.urgency);
+                notification
+            }
+            None => {
+                let notification = {
+                    let mut notifications = self.notifications.write();
+                    let Some(notification) = notifications.get_mut(&id) else {
+                        return;
+                    };
+                    if action == EscalationAction::Critical {
+                        notification.hints.urgency = Urgency::Critical;
+                        notification.expire_timeout = -1;
+                    }
+                    notification.clone()
+                };
 
-            // Check if notification is still active and not hovered
-            // Note: In real implementation, we'd need proper Arc handling here
-            debug!("Notification {} expired", id);
-            let _ = ui_sender.send(UiEvent::Close(id)).await;
-            let _ = close_sender.send((id, CloseReason::Expired)).await;
+                if action == EscalationAction::Critical {
+                    // Move up with the other critical notifications
+                    self.display_order.write().retain(|&other| other != id);
+                    self.insert_in_order(id, Urgency::Critical);
+                }
+                if action != EscalationAction::Sound && self.visible.read().contains(&id) {
+                    // Pop it up again as if it had just arrived
+                    let _ = self.ui_sender.send(UiEvent::Hide(id)).await;
+                    let _ = self
+                        .ui_sender
+                        .send(UiEvent::Show(notification.clone()))
+                        .await;
+                    self.start_timer(id);
+                }
+                notification
+            }
+        };
+
+        if action != EscalationAction::Sound {
+            self.update_visible().await;
+        }
+        self.emit(NotificationEvent::Escalated {
+            notification,
+            action,
+            round,
         });
     }
 
     /// Close a notification
     pub async fn close_notification(&self, id: u32, reason: CloseReason) {
-        let existed = {
-            let mut notifications = self.notifications.write();
-            notifications.remove(&id).is_some()
-        };
+        self.timers.cancel(id);
+
+        let removed = self.notifications.write().remove(&id);
 
-        if existed {
+        // Expiring is not a reaction, so the escalation goes on. Anything
+        // else ends it, even for a notification that already expired.
+        if !matches!(reason, CloseReason::Expired) {
+            self.escalations.cancel(id);
+        }
+
+        if let Some(notification) = removed {
             {
                 let mut order = self.display_order.write();
                 order.retain(|&x| x != id);
             }
+            self.visible.write().remove(&id);
 
             info!("Closed notification {} (reason: {:?})", id, reason);
+            if matches!(reason, CloseReason::Expired) {
+                self.escalations.park(id, notification.clone());
+            }
             let _ = self.ui_sender.send(UiEvent::Close(id)).await;
-            let _ = self.close_sender.send((id, reason)).await;
+            self.emit(NotificationEvent::Closed {
+                notification,
+                reason,
+            });
+
+            // Promote the next pending notification into the free slot
+            self.update_visible().await;
         }
     }
 
+    /// Get the shared configuration
+    pub fn config(&self) -> Arc<RwLock<Config>> {
+        self.config.clone()
+    }
+
     /// Get a notification by ID
     pub fn get_notification(&self, id: u32) -> Option<Notification> {
         self.notifications.read().get(&id).cloned()
@@ -249,17 +1141,25 @@ impl NotificationManager {
 
     /// Get all visible notifications in display order
     pub fn get_visible_notifications(&self) -> Vec<Notification> {
-        let config = self.config.read();
         let order = self.display_order.read();
         let notifications = self.notifications.read();
+        let visible = self.visible.read();
 
         order
             .iter()
-            .take(config.general.max_visible as usize)
+            .filter(|id| visible.contains(id))
             .filter_map(|id| notifications.get(id).cloned())
             .collect()
     }
 
+    /// Get the number of notifications waiting for a popup
+    pub fn pending_count(&self) -> usize {
+        self.notifications
+            .read()
+            .len()
+            .saturating_sub(self.visible.read().len())
+    }
+
     /// Get the count of active notifications
     pub fn count(&self) -> usize {
         self.notifications.read().len()
@@ -272,8 +1172,13 @@ impl NotificationManager {
 
     /// Dismiss all notifications
     pub async fn dismiss_all(&self) {
+        // Close pending notifications first so none get promoted on the way
         let ids = self.get_all_ids();
-        for id in ids {
+        let (visible, pending): (Vec<u32>, Vec<u32>) = {
+            let visible = self.visible.read();
+            ids.into_iter().partition(|id| visible.contains(id))
+        };
+        for id in pending.into_iter().chain(visible) {
             self.close_notification(id, CloseReason::Dismissed).await;
         }
         info!("Dismissed all notifications");
@@ -289,17 +1194,199 @@ impl NotificationManager {
                 .any(|(key, _)| key == action_key)
             {
                 info!("Action '{}' invoked on notification {}", action_key, id);
-                // The D-Bus server will emit the ActionInvoked signal
             }
+            self.escalations.cancel(id);
+            // The D-Bus server emits the ActionInvoked signal
+            self.emit(NotificationEvent::ActionInvoked {
+                id,
+                action_key: action_key.to_string(),
+            });
+        }
+    }
+
+    /// Handle an inline reply typed into a notification
+    pub async fn reply(&self, id: u32, text: &str) {
+        if self.notifications.read().contains_key(&id) {
+            info!("Inline reply for notification {}", id);
+            self.escalations.cancel(id);
+            self.emit(NotificationEvent::Replied {
+                id,
+                text: text.to_string(),
+            });
+        }
+    }
+
+    /// Close a notification once one of its actions was invoked
+    ///
+    /// Resident notifications stay until they are explicitly closed.
+    pub async fn close_after_action(&self, id: u32) {
+        if self.get_notification(id).is_some_and(|n| n.is_resident()) {
+            debug!("Keeping resident notification {} after action", id);
+            return;
         }
+        self.close_notification(id, CloseReason::Dismissed).await;
+    }
+
+    /// History row of a notification, if it was written to history
+    pub fn history_row(&self, id: u32) -> Option<i64> {
+        self.notifications
+            .read()
+            .get(&id)
+            .and_then(|n| n.history_row)
+    }
+
+    /// Invoke an action on a notification after a delay, as if the user had
+    /// clicked it
+    ///
+    /// Nothing happens if the notification is gone by then or never offered
+    /// the action.
+    pub fn schedule_action(self: &Arc<Self>, id: u32, action_key: String, delay_ms: u64) {
+        let Some(sender) = self.action_sender.clone() else {
+            debug!("No action channel, not invoking '{}' on {}", action_key, id);
+            return;
+        };
+
+        let manager = Arc::clone(self);
+        tokio::spawn(async move {
+            if delay_ms > 0 {
+                sleep(Duration::from_millis(delay_ms)).await;
+            }
+
+            let offered = manager
+                .get_notification(id)
+                .is_some_and(|n| n.actions.iter().any(|(key, _)| *key == action_key));
+            if !offered {
+                debug!(
+                    "Skipping auto-invoke of '{}': notification {} no longer offers it",
+                    action_key, id
+                );
+                return;
+            }
+
+            info!(
+                "Auto-invoking action '{}' on notification {}",
+                action_key, id
+            );
+            let event = if action_key == "default" {
+                ActionEvent::DefaultAction { id }
+            } else {
+                ActionEvent::ActionInvoked { id, action_key }
+            };
+            let _ = sender.send(event).await;
+        });
     }
 
     /// Set hover state for a notification
     pub fn set_hovered(&self, id: u32, hovered: bool) {
-        let mut notifications = self.notifications.write();
-        if let Some(notification) = notifications.get_mut(&id) {
-            notification.is_hovered = hovered;
-            debug!("Notification {} hover state: {}", id, hovered);
+        {
+            let mut notifications = self.notifications.write();
+            self.timers.set_hovered(id, hovered);
+            if let Some(notification) = notifications.get_mut(&id) {
+                notification.is_hovered = hovered;
+                self.update_expiry(notification);
+                debug!("Notification {} hover state: {}", id, hovered);
+            }
+        }
+        self.save_snapshot();
+    }
+
+    /// Check if Do Not Disturb keeps this notification from popping up
+    fn suppressed_by_dnd(&self, notification: &Notification) -> bool {
+        let Some(ref dnd) = self.dnd else {
+            return false;
+        };
+
+        dnd.is_enabled()
+            && !(notification.hints.urgency == Urgency::Critical
+                && self.config.read().dnd.allow_critical)
+    }
+
+    /// Number of notifications suppressed since DND was turned on
+    pub fn dnd_suppressed_count(&self) -> u32 {
+        self.dnd_suppressed.load(Ordering::SeqCst)
+    }
+
+    /// Follow DND changes and summarize what was missed when it ends
+    ///
+    /// Every change is announced as an event; turning DND off adds a popup
+    /// counting the suppressed notifications if `[dnd] summary` is set.
+    pub async fn watch_dnd(&self, mut changes: watch::Receiver<bool>) {
+        while changes.changed().await.is_ok() {
+            let enabled = *changes.borrow_and_update();
+            self.emit(NotificationEvent::DndChanged(enabled));
+            if enabled {
+                continue;
+            }
+
+            let count = self.dnd_suppressed.swap(0, Ordering::SeqCst);
+            if count == 0 {
+                continue;
+            }
+            info!("{} notifications suppressed during Do Not Disturb", count);
+
+            if self.config.read().dnd.summary {
+                let mut summary = Notification::new(
+                    0,
+                    "swaynoti".to_string(),
+                    0,
+                    String::new(),
+                    format!(
+                        "{} notification{} while in Do Not Disturb",
+                        count,
+                        if count == 1 { "" } else { "s" }
+                    ),
+                    "Open the notification center to see them".to_string(),
+                    Vec::new(),
+                    Default::default(),
+                    -1,
+                );
+                summary.skip_history = true;
+                self.add_notification(summary).await;
+            }
+        }
+    }
+
+    /// Freeze expiration timers while the user is idle
+    pub fn set_idle(&self, idle: bool) {
+        info!("User {}", if idle { "went idle" } else { "is back" });
+        self.timers.set_idle(idle);
+        for notification in self.notifications.write().values_mut() {
+            self.update_expiry(notification);
+        }
+        self.save_snapshot();
+    }
+}
+
+/// Timeout of a notification, falling back to the default for its urgency
+fn timeout_for(notification: &Notification, timeouts: &TimeoutConfig) -> i32 {
+    if notification.expire_timeout == 0 {
+        // Never expires
+        return 0;
+    }
+
+    if notification.expire_timeout > 0 {
+        // Use provided timeout
+        return notification.expire_timeout;
+    }
+
+    // Use server default based on urgency
+    match notification.hints.urgency {
+        Urgency::Low => timeouts.low,
+        Urgency::Normal => timeouts.normal,
+        Urgency::Critical => timeouts.critical,
+    }
+}
+
+/// Move a popup from the visible set back to the pending queue
+///
+/// A popup shown in the same pass is simply not shown; one already on screen
+/// is hidden.
+fn send_back(id: u32, visible: &mut HashSet<u32>, shown: &mut Vec<u32>, hidden: &mut Vec<u32>) {
+    visible.remove(&id);
+    match shown.iter().position(|&shown_id| shown_id == id) {
+        Some(pos) => {
+            shown.remove(pos);
         }
+        None => hidden.push(id),
     }
 }
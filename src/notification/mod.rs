@@ -1,8 +1,17 @@
+mod escalation;
+mod events;
 mod manager;
 #[allow(clippy::module_inception)]
 mod notification;
+mod rate_limit;
+mod snapshot;
+mod timer;
 mod urgency;
 
+pub use events::NotificationEvent;
 pub use manager::{ActionEvent, CloseReason, NotificationManager, UiEvent};
 pub use notification::{ImageData, Notification, NotificationHints};
+#[allow(unused_imports)]
+pub use rate_limit::ThrottleStatus;
+pub use snapshot::Snapshot;
 pub use urgency::Urgency;
@@ -1,8 +1,12 @@
+use std::collections::HashMap;
+
 use super::Urgency;
+use crate::config::{Anchor, DisplayMode, EscalationPolicy};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 /// Core notification structure
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Notification {
     /// Unique notification ID assigned by the daemon
     pub id: u32,
@@ -38,7 +42,50 @@ pub struct Notification {
     pub created_at: DateTime<Utc>,
 
     /// Whether the notification is currently being hovered
+    #[serde(skip)]
     pub is_hovered: bool,
+
+    /// Anchor override from a matching rule
+    pub anchor: Option<Anchor>,
+
+    /// Extra CSS class(es) from a matching rule
+    pub css_class: Option<String>,
+
+    /// Don't write this notification to history
+    pub skip_history: bool,
+
+    /// Don't play a sound for this notification
+    pub skip_sound: bool,
+
+    /// Whether to show, drop or only record this notification
+    pub display: DisplayMode,
+
+    /// Summary and body for history, when a rule rewrote them differently
+    pub history_text: Option<(String, String)>,
+
+    /// Icon name or path override from a matching rule
+    pub icon: Option<String>,
+
+    /// App name to display instead of `app_name`
+    pub display_name: Option<String>,
+
+    /// Sound file or theme sound name from a matching rule
+    pub sound: Option<String>,
+
+    /// How many identical notifications this one stands for
+    pub repeat_count: u32,
+
+    /// Popup stack key from a matching rule
+    pub group: Option<String>,
+
+    /// Popup limit for this app from a matching rule
+    pub max_visible_per_app: Option<u32>,
+
+    /// Escalation from a matching rule
+    pub escalation: Option<EscalationPolicy>,
+
+    /// Key of this notification's row in the history database
+    pub history_row: Option<i64>,
 }
 
 impl Notification {
@@ -80,6 +127,20 @@ impl Notification {
             expires_at: None,
             created_at: Utc::now(),
             is_hovered: false,
+            anchor: None,
+            css_class: None,
+            skip_history: false,
+            skip_sound: false,
+            display: DisplayMode::Show,
+            history_text: None,
+            icon: None,
+            display_name: None,
+            sound: None,
+            repeat_count: 1,
+            group: None,
+            max_visible_per_app: None,
+            escalation: None,
+            history_row: None,
         }
     }
 
@@ -95,7 +156,30 @@ impl Notification {
 
     /// Check if this notification should be persisted in history
     pub fn should_persist(&self) -> bool {
-        !self.hints.transient
+        !self.hints.transient && !self.skip_history && self.display != DisplayMode::Drop
+    }
+
+    /// Key of the popup stack this notification joins when grouping is on
+    pub fn group_key(&self) -> &str {
+        self.group.as_deref().unwrap_or(&self.app_name)
+    }
+
+    /// App name to show in the popup
+    pub fn display_app_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.app_name)
+    }
+
+    /// Summary and body to record in history
+    pub fn history_summary_body(&self) -> (&str, &str) {
+        match self.history_text {
+            Some((ref summary, ref body)) => (summary, body),
+            None => (&self.summary, &self.body),
+        }
+    }
+
+    /// Check if this notification should be shown as a popup
+    pub fn should_display(&self) -> bool {
+        self.display == DisplayMode::Show
     }
 
     /// Check if this notification is resident (stays until dismissed)
@@ -105,7 +189,7 @@ impl Notification {
 }
 
 /// Notification hints parsed from D-Bus
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct NotificationHints {
     /// Urgency level (0=low, 1=normal, 2=critical)
     pub urgency: Urgency,
@@ -116,7 +200,8 @@ pub struct NotificationHints {
     /// Desktop entry name
     pub desktop_entry: Option<String>,
 
-    /// Image data (raw pixels)
+    /// Image data (raw pixels), too large to keep in the snapshot
+    #[serde(skip)]
     pub image_data: Option<ImageData>,
 
     /// Image path
@@ -151,6 +236,65 @@ pub struct NotificationHints {
 
     /// Inline reply support (for messaging apps)
     pub inline_reply: bool,
+
+    /// Other hints (e.g. `x-kde-origin-name`) rendered as strings
+    pub extra: HashMap<String, String>,
+}
+
+impl NotificationHints {
+    /// Look up a hint value by its D-Bus name, rendered as a string
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "urgency" => Some(self.urgency.to_string()),
+            "category" => self.category.clone(),
+            "desktop-entry" => self.desktop_entry.clone(),
+            "image-path" | "image_path" => self.image_path.clone(),
+            "sound-file" => self.sound_file.clone(),
+            "sound-name" => self.sound_name.clone(),
+            "suppress-sound" => Some(self.suppress_sound.to_string()),
+            "transient" => Some(self.transient.to_string()),
+            "resident" => Some(self.resident.to_string()),
+            "action-icons" => Some(self.action_icons.to_string()),
+            "x" => self.x.map(|x| x.to_string()),
+            "y" => self.y.map(|y| y.to_string()),
+            "value" => self.value.map(|v| v.to_string()),
+            _ => self.extra.get(key).cloned(),
+        }
+    }
+
+    /// Set a hint from its string form (the inverse of [`Self::get`])
+    ///
+    /// Returns `false` if the value can't be parsed for that hint.
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        fn parse<T: std::str::FromStr>(value: &str, field: &mut T) -> bool {
+            value.parse().map(|v| *field = v).is_ok()
+        }
+
+        match key {
+            "urgency" => match value.to_lowercase().as_str() {
+                "low" | "0" => self.urgency = Urgency::Low,
+                "normal" | "1" => self.urgency = Urgency::Normal,
+                "critical" | "2" => self.urgency = Urgency::Critical,
+                _ => return false,
+            },
+            "category" => self.category = Some(value.to_string()),
+            "desktop-entry" => self.desktop_entry = Some(value.to_string()),
+            "image-path" | "image_path" => self.image_path = Some(value.to_string()),
+            "sound-file" => self.sound_file = Some(value.to_string()),
+            "sound-name" => self.sound_name = Some(value.to_string()),
+            "suppress-sound" => return parse(value, &mut self.suppress_sound),
+            "transient" => return parse(value, &mut self.transient),
+            "resident" => return parse(value, &mut self.resident),
+            "action-icons" => return parse(value, &mut self.action_icons),
+            "x" => return value.parse().map(|x| self.x = Some(x)).is_ok(),
+            "y" => return value.parse().map(|y| self.y = Some(y)).is_ok(),
+            "value" => return value.parse().map(|v| self.value = Some(v)).is_ok(),
+            _ => {
+                self.extra.insert(key.to_string(), value.to_string());
+            }
+        }
+        true
+    }
 }
 
 /// Raw image data from D-Bus
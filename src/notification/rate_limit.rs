@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::config::RateLimitConfig;
+
+/// Token bucket of one app
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+    /// Notifications dropped since the current summary popup appeared
+    dropped: u32,
+    /// Notifications dropped since the daemon started
+    total_dropped: u64,
+    /// Popup summarizing the dropped notifications
+    summary_id: Option<u32>,
+}
+
+/// Outcome of checking a notification against its app's bucket
+pub enum Throttle {
+    /// Within the limit
+    Allowed,
+    /// Over the limit; the summary popup should now report `dropped`
+    Dropped {
+        dropped: u32,
+        summary_id: Option<u32>,
+    },
+}
+
+/// Throttling state of one app, reported over IPC
+#[derive(Debug, Clone, Serialize)]
+pub struct ThrottleStatus {
+    pub app_name: String,
+    /// Whether the app is currently out of tokens
+    pub throttled: bool,
+    /// Notifications dropped in the current flood
+    pub dropped: u32,
+    /// Notifications dropped since the daemon started
+    pub total_dropped: u64,
+}
+
+/// Per-app token-bucket rate limiting
+///
+/// Every app starts with `burst` tokens that refill at `per_second`. Each
+/// notification takes a token; without one it is dropped and counted so a
+/// single summary popup can report the flood.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Take a token for a notification from `app_name`
+    ///
+    /// `summary_active` tells whether a previous summary popup is still
+    /// showing, in which case its count keeps growing.
+    pub fn check(
+        &self,
+        app_name: &str,
+        config: &RateLimitConfig,
+        summary_active: impl Fn(u32) -> bool,
+    ) -> Throttle {
+        let (burst, per_second) = match config.apps.get(app_name) {
+            Some(limit) => (limit.burst, limit.per_second),
+            None => (config.burst, config.per_second),
+        };
+        let burst = burst.max(1) as f64;
+
+        let mut buckets = self.buckets.lock();
+        let now = Instant::now();
+        let bucket = buckets
+            .entry(app_name.to_string())
+            .or_insert_with(|| Bucket {
+                tokens: burst,
+                last_refill: now,
+                dropped: 0,
+                total_dropped: 0,
+                summary_id: None,
+            });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second.max(0.0)).min(burst);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Throttle::Allowed;
+        }
+
+        // Start counting again once the previous summary is gone
+        if !bucket.summary_id.is_some_and(&summary_active) {
+            bucket.dropped = 0;
+            bucket.summary_id = None;
+        }
+        bucket.dropped += 1;
+        bucket.total_dropped += 1;
+
+        Throttle::Dropped {
+            dropped: bucket.dropped,
+            summary_id: bucket.summary_id,
+        }
+    }
+
+    /// Remember the popup that summarizes an app's dropped notifications
+    pub fn set_summary_id(&self, app_name: &str, id: u32) {
+        if let Some(bucket) = self.buckets.lock().get_mut(app_name) {
+            bucket.summary_id = Some(id);
+        }
+    }
+
+    /// Throttling state of every app that has dropped notifications
+    pub fn status(&self) -> Vec<ThrottleStatus> {
+        let mut status: Vec<_> = self
+            .buckets
+            .lock()
+            .iter()
+            .filter(|(_, bucket)| bucket.total_dropped > 0)
+            .map(|(app_name, bucket)| ThrottleStatus {
+                app_name: app_name.clone(),
+                throttled: bucket.tokens < 1.0,
+                dropped: bucket.dropped,
+                total_dropped: bucket.total_dropped,
+            })
+            .collect();
+        status.sort_by(|a, b| a.app_name.cmp(&b.app_name));
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::config::AppRateLimit;
+
+    fn config(burst: u32, per_second: f64) -> RateLimitConfig {
+        RateLimitConfig {
+            enabled: true,
+            burst,
+            per_second,
+            ..Default::default()
+        }
+    }
+
+    fn allowed(limiter: &RateLimiter, app_name: &str, config: &RateLimitConfig) -> bool {
+        matches!(limiter.check(app_name, config, |_| true), Throttle::Allowed)
+    }
+
+    #[test]
+    fn burst_then_drop() {
+        let limiter = RateLimiter::default();
+        let config = config(3, 0.0);
+
+        for _ in 0..3 {
+            assert!(allowed(&limiter, "app", &config));
+        }
+        assert!(matches!(
+            limiter.check("app", &config, |_| true),
+            Throttle::Dropped {
+                dropped: 1,
+                summary_id: None
+            }
+        ));
+
+        // The summary popup keeps counting while it is on screen
+        limiter.set_summary_id("app", 42);
+        assert!(matches!(
+            limiter.check("app", &config, |id| id == 42),
+            Throttle::Dropped {
+                dropped: 2,
+                summary_id: Some(42)
+            }
+        ));
+
+        // Once it is gone, a new flood starts a new summary
+        assert!(matches!(
+            limiter.check("app", &config, |_| false),
+            Throttle::Dropped {
+                dropped: 1,
+                summary_id: None
+            }
+        ));
+    }
+
+    #[test]
+    fn tokens_refill_up_to_the_burst() {
+        let limiter = RateLimiter::default();
+        let config = config(1, 20.0);
+
+        assert!(allowed(&limiter, "app", &config));
+        assert!(!allowed(&limiter, "app", &config));
+
+        // Long enough for several tokens, but the bucket holds only one
+        sleep(Duration::from_millis(200));
+        assert!(allowed(&limiter, "app", &config));
+        assert!(!allowed(&limiter, "app", &config));
+    }
+
+    #[test]
+    fn apps_have_their_own_buckets() {
+        let limiter = RateLimiter::default();
+        let mut config = config(2, 0.0);
+        config.apps.insert(
+            "Spammy".to_string(),
+            AppRateLimit {
+                burst: 1,
+                per_second: 0.0,
+            },
+        );
+
+        assert!(allowed(&limiter, "Spammy", &config));
+        assert!(!allowed(&limiter, "Spammy", &config));
+        assert!(allowed(&limiter, "Other", &config));
+        assert!(allowed(&limiter, "Other", &config));
+
+        let status = limiter.status();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].app_name, "Spammy");
+        assert!(status[0].throttled);
+        assert_eq!(status[0].total_dropped, 1);
+    }
+}
@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, error};
+
+use super::Notification;
+use crate::config::ConfigLoader;
+
+/// How long to collect changes before writing them out
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// On-disk format of the snapshot
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile {
+    /// Active notifications in display order
+    notifications: Vec<Notification>,
+}
+
+/// Active notifications saved to disk so they survive a daemon restart
+///
+/// [`Snapshot::save`] only queues the latest state; [`Snapshot::run`] writes
+/// it off the async threads once changes settle, so a burst of notifications
+/// costs one write.
+pub struct Snapshot {
+    path: PathBuf,
+    /// Latest state not written yet
+    pending: Mutex<Option<Vec<Notification>>>,
+    /// Wakes the writer when there is something to save
+    changed: Notify,
+    /// Makes every temporary file name unique
+    next_tmp: AtomicU64,
+}
+
+impl Snapshot {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            pending: Mutex::new(None),
+            changed: Notify::new(),
+            next_tmp: AtomicU64::new(0),
+        }
+    }
+
+    /// Snapshot in the data directory, next to the history database
+    pub fn default_location() -> Option<Self> {
+        ConfigLoader::data_dir().map(|dir| Self::new(dir.join("active.json")))
+    }
+
+    /// Replace the snapshot with these notifications, soon
+    pub fn save(&self, notifications: Vec<Notification>) {
+        *self.pending.lock() = Some(notifications);
+        self.changed.notify_one();
+    }
+
+    /// Write the latest queued state once no change came in for a moment
+    pub async fn run(&self) {
+        loop {
+            self.changed.notified().await;
+            sleep(SAVE_DELAY).await;
+
+            let Some(notifications) = self.pending.lock().take() else {
+                continue;
+            };
+            let path = self.path.clone();
+            let tmp = self.tmp_path();
+            let written =
+                tokio::task::spawn_blocking(move || write(&path, &tmp, notifications)).await;
+            match written {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("Failed to save active notifications: {:#}", e),
+                Err(e) => error!("Snapshot writer failed: {}", e),
+            }
+        }
+    }
+
+    /// Write a state that is still queued right away, e.g. on shutdown
+    pub fn flush(&self) {
+        if let Some(notifications) = self.pending.lock().take() {
+            if let Err(e) = write(&self.path, &self.tmp_path(), notifications) {
+                error!("Failed to save active notifications: {:#}", e);
+            }
+        }
+    }
+
+    /// Read the saved notifications, or none if there is no snapshot
+    pub fn load(&self) -> Result<Vec<Notification>> {
+        let json = match std::fs::read(&self.path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", self.path)),
+        };
+
+        let file: SnapshotFile = serde_json::from_slice(&json)
+            .with_context(|| format!("Failed to parse {:?}", self.path))?;
+        Ok(file.notifications)
+    }
+
+    /// Temporary file no other write uses
+    fn tmp_path(&self) -> PathBuf {
+        let n = self.next_tmp.fetch_add(1, Ordering::Relaxed);
+        self.path
+            .with_extension(format!("json.{}.{}.tmp", std::process::id(), n))
+    }
+}
+
+/// Write the snapshot file, replacing the previous one atomically
+fn write(path: &Path, tmp: &Path, notifications: Vec<Notification>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let count = notifications.len();
+    let json = serde_json::to_vec(&SnapshotFile { notifications })?;
+
+    // Write and rename so a crash never leaves a truncated snapshot
+    std::fs::write(tmp, json).with_context(|| format!("Failed to write {:?}", tmp))?;
+    std::fs::rename(tmp, path).with_context(|| format!("Failed to replace {:?}", path))?;
+
+    debug!("Saved {} active notifications to {:?}", count, path);
+    Ok(())
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use async_channel::{Receiver, Sender};
+use parking_lot::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use tracing::debug;
+
+/// A timer that ran out: notification ID and the generation it was started for
+pub type Expiry = (u32, u64);
+
+/// State of one notification's expiration timer
+struct Timer {
+    /// Bumped every time the sleeper is (re)started, so stale expiries are ignored
+    generation: u64,
+    /// Time left when the sleeper was last started
+    remaining: Duration,
+    /// When the running sleeper started, `None` while paused
+    started: Option<Instant>,
+    /// Paused because the pointer is over the popup
+    hovered: bool,
+    /// Sleeper task for the running timer
+    task: Option<JoinHandle<()>>,
+}
+
+impl Timer {
+    fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Time left, counting down while the sleeper runs
+    fn time_left(&self) -> Duration {
+        match self.started {
+            Some(started) => self.remaining.saturating_sub(started.elapsed()),
+            None => self.remaining,
+        }
+    }
+
+    /// Stop the sleeper and keep the time that was left
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        self.remaining = self.time_left();
+        self.started = None;
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+/// Timers plus the pause state shared by all of them
+struct TimerState {
+    timers: HashMap<u32, Timer>,
+    /// The user is away, so no timer counts down
+    idle: bool,
+    next_generation: u64,
+}
+
+/// Cancellable expiration timers, one per notification ID
+///
+/// Timers pause while their popup is hovered, and all of them freeze while
+/// the user is idle; either way they resume with the time that was left.
+/// When a timer runs out its ID is sent on the expiry channel; the manager
+/// confirms it with [`ExpiryTimers::take_expired`] before closing, so a timer
+/// restarted in the meantime never closes the new content.
+pub struct ExpiryTimers {
+    state: Mutex<TimerState>,
+    expired_sender: Sender<Expiry>,
+}
+
+impl ExpiryTimers {
+    pub fn new() -> (Self, Receiver<Expiry>) {
+        let (expired_sender, expired_receiver) = async_channel::unbounded();
+        let timers = Self {
+            state: Mutex::new(TimerState {
+                timers: HashMap::new(),
+                idle: false,
+                next_generation: 0,
+            }),
+            expired_sender,
+        };
+        (timers, expired_receiver)
+    }
+
+    /// Start (or restart) the timer for a notification
+    ///
+    /// A hovered notification keeps its timer paused until unhovered.
+    pub fn start(&self, id: u32, timeout: Duration, hovered: bool) {
+        let mut state = self.state.lock();
+        let mut timer = Timer {
+            generation: 0,
+            remaining: timeout,
+            started: None,
+            hovered,
+            task: None,
+        };
+        self.update(&mut state, id, &mut timer);
+
+        debug!(
+            "Expiration timer for {} set to {} ms{}",
+            id,
+            timeout.as_millis(),
+            if timer.is_running() { "" } else { " (paused)" }
+        );
+        // Dropping a previous timer aborts its sleeper
+        state.timers.insert(id, timer);
+    }
+
+    /// Cancel the timer for a notification, if any
+    pub fn cancel(&self, id: u32) {
+        if self.state.lock().timers.remove(&id).is_some() {
+            debug!("Expiration timer for {} cancelled", id);
+        }
+    }
+
+    /// Pause or resume a timer as the pointer enters or leaves the popup
+    pub fn set_hovered(&self, id: u32, hovered: bool) {
+        let mut state = self.state.lock();
+        let Some(mut timer) = state.timers.remove(&id) else {
+            return;
+        };
+
+        timer.hovered = hovered;
+        self.update(&mut state, id, &mut timer);
+        state.timers.insert(id, timer);
+    }
+
+    /// Freeze all timers while the user is idle
+    pub fn set_idle(&self, idle: bool) {
+        let mut state = self.state.lock();
+        if state.idle == idle {
+            return;
+        }
+        state.idle = idle;
+
+        let mut timers = std::mem::take(&mut state.timers);
+        for (&id, timer) in timers.iter_mut() {
+            self.update(&mut state, id, timer);
+        }
+        state.timers = timers;
+
+        debug!(
+            "Expiration timers {}",
+            if idle { "frozen" } else { "resumed" }
+        );
+    }
+
+    /// Time left on a notification's timer and whether it is counting down
+    pub fn remaining(&self, id: u32) -> Option<(Duration, bool)> {
+        self.state
+            .lock()
+            .timers
+            .get(&id)
+            .map(|timer| (timer.time_left(), timer.is_running()))
+    }
+
+    /// Check that an expiry is still current and forget the timer if so
+    pub fn take_expired(&self, (id, generation): Expiry) -> bool {
+        let mut state = self.state.lock();
+        match state.timers.get(&id) {
+            Some(timer) if timer.generation == generation && timer.is_running() => {
+                state.timers.remove(&id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Start or stop a timer's sleeper to match the pause state
+    fn update(&self, state: &mut TimerState, id: u32, timer: &mut Timer) {
+        let should_run = !timer.hovered && !state.idle;
+
+        if !should_run && timer.is_running() {
+            timer.stop();
+            debug!(
+                "Expiration timer for {} paused with {} ms left",
+                id,
+                timer.remaining.as_millis()
+            );
+        } else if should_run && !timer.is_running() {
+            state.next_generation += 1;
+            timer.generation = state.next_generation;
+            self.run(id, timer);
+        }
+    }
+
+    /// Spawn the sleeper for the time left on a timer
+    fn run(&self, id: u32, timer: &mut Timer) {
+        let sender = self.expired_sender.clone();
+        let generation = timer.generation;
+        let remaining = timer.remaining;
+
+        timer.started = Some(Instant::now());
+        timer.task = Some(tokio::spawn(async move {
+            sleep(remaining).await;
+            let _ = sender.send((id, generation)).await;
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wait for the next expiry, or `None` if nothing expires in time
+    async fn next_expiry(receiver: &Receiver<Expiry>, within: Duration) -> Option<Expiry> {
+        tokio::time::timeout(within, receiver.recv())
+            .await
+            .ok()
+            .and_then(Result::ok)
+    }
+
+    #[tokio::test]
+    async fn expires_after_the_timeout() {
+        let (timers, expired) = ExpiryTimers::new();
+        timers.start(1, Duration::from_millis(50), false);
+
+        let expiry = next_expiry(&expired, Duration::from_millis(500)).await;
+        assert!(timers.take_expired(expiry.unwrap()));
+        assert!(timers.remaining(1).is_none());
+    }
+
+    #[tokio::test]
+    async fn cancel_and_restart_win_over_the_old_timer() {
+        let (timers, expired) = ExpiryTimers::new();
+        timers.start(1, Duration::from_millis(50), false);
+        timers.cancel(1);
+        assert!(next_expiry(&expired, Duration::from_millis(150))
+            .await
+            .is_none());
+
+        timers.start(2, Duration::from_millis(20), false);
+        let stale = next_expiry(&expired, Duration::from_millis(500))
+            .await
+            .unwrap();
+        timers.start(2, Duration::from_millis(50), false);
+        assert!(!timers.take_expired(stale));
+
+        let expiry = next_expiry(&expired, Duration::from_millis(500)).await;
+        assert!(timers.take_expired(expiry.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn hover_pauses_with_the_time_left() {
+        let (timers, expired) = ExpiryTimers::new();
+        timers.start(1, Duration::from_millis(300), false);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        timers.set_hovered(1, true);
+        let (left, running) = timers.remaining(1).unwrap();
+        assert!(!running);
+        assert!(left <= Duration::from_millis(150));
+        assert!(next_expiry(&expired, Duration::from_millis(300))
+            .await
+            .is_none());
+
+        timers.set_hovered(1, false);
+        let expiry = next_expiry(&expired, left + Duration::from_millis(250)).await;
+        assert!(timers.take_expired(expiry.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn idle_freezes_every_timer() {
+        let (timers, expired) = ExpiryTimers::new();
+        timers.start(1, Duration::from_millis(300), false);
+        timers.start(2, Duration::from_millis(300), true);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        timers.set_idle(true);
+        assert!(!timers.remaining(1).unwrap().1);
+        assert!(next_expiry(&expired, Duration::from_millis(300))
+            .await
+            .is_none());
+
+        // Back from idle, the running timer goes on where it stopped and
+        // the hovered one stays paused
+        timers.set_idle(false);
+        let (left, running) = timers.remaining(1).unwrap();
+        assert!(running);
+        assert!(left <= Duration::from_millis(150));
+        assert_eq!(
+            timers.remaining(2),
+            Some((Duration::from_millis(300), false))
+        );
+
+        let expiry = next_expiry(&expired, left + Duration::from_millis(250))
+            .await
+            .unwrap();
+        assert_eq!(expiry.0, 1);
+        assert!(timers.take_expired(expiry));
+    }
+}
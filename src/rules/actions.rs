@@ -1,6 +1,34 @@
 use crate::config::RuleActions;
 use crate::notification::{Notification, Urgency};
 
+/// Merge the actions of a later rule on top of earlier ones
+///
+/// Every action set by `later` overrides the same action from `base`,
+/// except `rewrite`, which accumulates so all substitutions run in order.
+pub fn merge_rule_actions(base: &mut RuleActions, later: &RuleActions) {
+    base.timeout = later.timeout.or(base.timeout);
+    base.urgency = later.urgency.clone().or(base.urgency.take());
+    base.anchor = later.anchor.or(base.anchor);
+    base.skip_history = later.skip_history.or(base.skip_history);
+    base.skip_sound = later.skip_sound.or(base.skip_sound);
+    base.css_class = later.css_class.clone().or(base.css_class.take());
+    base.display = later.display.or(base.display);
+    base.exec = later.exec.clone().or(base.exec.take());
+    base.exec_timeout = later.exec_timeout.or(base.exec_timeout);
+    base.invoke_action = later.invoke_action.clone().or(base.invoke_action.take());
+    base.invoke_delay = later.invoke_delay.or(base.invoke_delay);
+    base.icon = later.icon.clone().or(base.icon.take());
+    base.app_display_name = later
+        .app_display_name
+        .clone()
+        .or(base.app_display_name.take());
+    base.sound = later.sound.clone().or(base.sound.take());
+    base.group = later.group.clone().or(base.group.take());
+    base.max_visible_per_app = later.max_visible_per_app.or(base.max_visible_per_app);
+    base.escalate = later.escalate.clone().or(base.escalate.take());
+    base.rewrite.extend(later.rewrite.iter().cloned());
+}
+
 /// Apply rule actions to a notification
 pub fn apply_rule_actions(notification: &mut Notification, actions: &RuleActions) {
     // Override timeout
@@ -18,6 +46,48 @@ pub fn apply_rule_actions(notification: &mut Notification, actions: &RuleActions
         };
     }
 
-    // Note: Other actions like skip_history, skip_sound, css_class
-    // are handled elsewhere in the notification pipeline
+    // Presentation overrides, picked up by the manager, history and UI
+    if let Some(anchor) = actions.anchor {
+        notification.anchor = Some(anchor);
+    }
+
+    if let Some(skip_history) = actions.skip_history {
+        notification.skip_history = skip_history;
+    }
+
+    if let Some(skip_sound) = actions.skip_sound {
+        notification.skip_sound = skip_sound;
+    }
+
+    if let Some(ref css_class) = actions.css_class {
+        notification.css_class = Some(css_class.clone());
+    }
+
+    if let Some(display) = actions.display {
+        notification.display = display;
+    }
+
+    if let Some(ref icon) = actions.icon {
+        notification.icon = Some(icon.clone());
+    }
+
+    if let Some(ref name) = actions.app_display_name {
+        notification.display_name = Some(name.clone());
+    }
+
+    if let Some(ref sound) = actions.sound {
+        notification.sound = Some(sound.clone());
+    }
+
+    if let Some(ref group) = actions.group {
+        notification.group = Some(group.clone());
+    }
+
+    if let Some(max) = actions.max_visible_per_app {
+        notification.max_visible_per_app = Some(max);
+    }
+
+    if let Some(ref escalate) = actions.escalate {
+        notification.escalation = Some(escalate.clone());
+    }
 }
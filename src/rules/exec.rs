@@ -0,0 +1,89 @@
+use std::process::Stdio;
+
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+use tracing::{debug, warn};
+
+use crate::notification::Notification;
+
+/// Default time a rule command may run before it is killed
+pub const DEFAULT_EXEC_TIMEOUT_MS: u64 = 5000;
+
+/// Run a rule's `exec` command for a notification in the background
+///
+/// The notification fields are exported as `SWAYNOTI_*` environment
+/// variables and written as a JSON object to the command's stdin.
+pub fn spawn_rule_command(command: &str, timeout_ms: u64, notification: &Notification) {
+    let command = command.to_string();
+    let category = notification.hints.category.clone().unwrap_or_default();
+    let desktop_entry = notification.hints.desktop_entry.clone().unwrap_or_default();
+
+    let payload = serde_json::json!({
+        "id": notification.id,
+        "app_name": notification.app_name,
+        "summary": notification.summary,
+        "body": notification.body,
+        "urgency": notification.hints.urgency.to_string(),
+        "category": category,
+        "desktop_entry": desktop_entry,
+    })
+    .to_string();
+
+    let env = [
+        ("SWAYNOTI_ID", notification.id.to_string()),
+        ("SWAYNOTI_APP_NAME", notification.app_name.clone()),
+        ("SWAYNOTI_SUMMARY", notification.summary.clone()),
+        ("SWAYNOTI_BODY", notification.body.clone()),
+        ("SWAYNOTI_URGENCY", notification.hints.urgency.to_string()),
+        ("SWAYNOTI_CATEGORY", category),
+        ("SWAYNOTI_DESKTOP_ENTRY", desktop_entry),
+    ];
+
+    tokio::spawn(async move {
+        let mut child = match Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                warn!("Failed to run rule command '{}': {}", command, e);
+                return;
+            }
+        };
+
+        let result = timeout(Duration::from_millis(timeout_ms), async {
+            if let Some(mut stdin) = child.stdin.take() {
+                // The command may not read stdin at all, so ignore broken pipes
+                let _ = stdin.write_all(payload.as_bytes()).await;
+            }
+            child.wait().await
+        })
+        .await;
+
+        match result {
+            Ok(Ok(status)) if status.success() => {
+                debug!("Rule command '{}' finished: {}", command, status);
+            }
+            Ok(Ok(status)) => {
+                warn!("Rule command '{}' failed: {}", command, status);
+            }
+            Ok(Err(e)) => {
+                warn!("Failed to wait for rule command '{}': {}", command, e);
+            }
+            Err(_) => {
+                warn!(
+                    "Rule command '{}' timed out after {}ms, killing it",
+                    command, timeout_ms
+                );
+                let _ = child.kill().await;
+            }
+        }
+    });
+}
@@ -1,88 +1,355 @@
+use chrono::{Local, NaiveTime};
 use regex::Regex;
-use tracing::debug;
 
-use crate::config::{AppRule, RuleCriteria};
-use crate::notification::Notification;
+use super::RuleError;
+use crate::config::RuleCriteria;
+use crate::notification::{Notification, Urgency};
 
-/// Matches notifications against rules
-pub struct RuleMatcher;
+/// Compiled match criteria of a single rule
+#[derive(Debug, Clone, Default)]
+pub struct RuleMatcher {
+    app_name: Option<Regex>,
+    summary: Option<Regex>,
+    body: Option<Regex>,
+    urgency: Option<Urgency>,
+    category: Option<Regex>,
+    desktop_entry: Option<Regex>,
+    hints: Vec<(String, Regex)>,
+    has_action: Option<Regex>,
+    transient: Option<bool>,
+    resident: Option<bool>,
+    time_start: Option<NaiveTime>,
+    time_end: Option<NaiveTime>,
+    not: Option<Box<RuleMatcher>>,
+    any: Vec<RuleMatcher>,
+}
 
 impl RuleMatcher {
-    /// Find the first matching rule for a notification
-    pub fn find_matching_rule<'a>(
-        notification: &Notification,
-        rules: &'a [AppRule],
-    ) -> Option<&'a AppRule> {
-        for rule in rules {
-            if Self::matches(&rule.criteria, notification) {
-                debug!(
-                    "Notification {} matched rule for app '{:?}'",
-                    notification.id, rule.criteria.app_name
-                );
-                return Some(rule);
-            }
+    /// Compile criteria of the rule at `index`; `path` prefixes field names in errors
+    pub fn compile(criteria: &RuleCriteria, index: usize, path: &str) -> Result<Self, RuleError> {
+        let pattern = |field: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|p| Self::compile_pattern(p, index, &format!("{}.{}", path, field)))
+                .transpose()
+        };
+        let time = |field: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|t| {
+                    NaiveTime::parse_from_str(t, "%H:%M").map_err(|_| RuleError::Time {
+                        index,
+                        field: format!("{}.{}", path, field),
+                        value: t.to_string(),
+                    })
+                })
+                .transpose()
+        };
+
+        let urgency = criteria
+            .urgency
+            .as_deref()
+            .map(|u| parse_urgency(u, index, &format!("{}.urgency", path)))
+            .transpose()?;
+
+        let mut hints = Vec::with_capacity(criteria.hints.len());
+        for (key, value) in &criteria.hints {
+            let field = format!("{}.hints.{}", path, key);
+            hints.push((key.clone(), Self::compile_pattern(value, index, &field)?));
         }
-        None
+
+        let not = criteria
+            .not
+            .as_deref()
+            .map(|not| Self::compile(not, index, &format!("{}.not", path)).map(Box::new))
+            .transpose()?;
+
+        let any = criteria
+            .any
+            .iter()
+            .enumerate()
+            .map(|(i, alt)| Self::compile(alt, index, &format!("{}.any[{}]", path, i)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            app_name: pattern("app_name", &criteria.app_name)?,
+            summary: pattern("summary", &criteria.summary)?,
+            body: pattern("body", &criteria.body)?,
+            urgency,
+            category: pattern("category", &criteria.category)?,
+            desktop_entry: pattern("desktop_entry", &criteria.desktop_entry)?,
+            hints,
+            has_action: pattern("has_action", &criteria.has_action)?,
+            transient: criteria.transient,
+            resident: criteria.resident,
+            time_start: time("time_start", &criteria.time_start)?,
+            time_end: time("time_end", &criteria.time_end)?,
+            not,
+            any,
+        })
+    }
+
+    fn compile_pattern(pattern: &str, index: usize, field: &str) -> Result<Regex, RuleError> {
+        Regex::new(pattern).map_err(|source| RuleError::Regex {
+            index,
+            field: field.to_string(),
+            source,
+        })
     }
 
-    /// Check if a notification matches the given criteria
-    fn matches(criteria: &RuleCriteria, notification: &Notification) -> bool {
+    /// Check if a notification matches these criteria
+    pub fn matches(&self, notification: &Notification) -> bool {
         // Check app_name
-        if let Some(ref pattern) = criteria.app_name {
-            if !Self::matches_pattern(pattern, &notification.app_name) {
+        if let Some(ref regex) = self.app_name {
+            if !regex.is_match(&notification.app_name) {
                 return false;
             }
         }
 
         // Check summary
-        if let Some(ref pattern) = criteria.summary {
-            if !Self::matches_pattern(pattern, &notification.summary) {
+        if let Some(ref regex) = self.summary {
+            if !regex.is_match(&notification.summary) {
                 return false;
             }
         }
 
         // Check body
-        if let Some(ref pattern) = criteria.body {
-            if !Self::matches_pattern(pattern, &notification.body) {
+        if let Some(ref regex) = self.body {
+            if !regex.is_match(&notification.body) {
                 return false;
             }
         }
 
         // Check urgency
-        if let Some(ref urgency_str) = criteria.urgency {
-            let urgency_matches = match urgency_str.to_lowercase().as_str() {
-                "low" => notification.hints.urgency == crate::notification::Urgency::Low,
-                "normal" => notification.hints.urgency == crate::notification::Urgency::Normal,
-                "critical" => notification.hints.urgency == crate::notification::Urgency::Critical,
-                _ => true,
-            };
-            if !urgency_matches {
+        if let Some(urgency) = self.urgency {
+            if notification.hints.urgency != urgency {
                 return false;
             }
         }
 
         // Check category
-        if let Some(ref category) = criteria.category {
-            if let Some(ref notif_category) = notification.hints.category {
-                if !Self::matches_pattern(category, notif_category) {
-                    return false;
-                }
-            } else {
+        if let Some(ref regex) = self.category {
+            match notification.hints.category {
+                Some(ref category) if regex.is_match(category) => {}
+                _ => return false,
+            }
+        }
+
+        // Check desktop entry
+        if let Some(ref regex) = self.desktop_entry {
+            match notification.hints.desktop_entry {
+                Some(ref entry) if regex.is_match(entry) => {}
+                _ => return false,
+            }
+        }
+
+        // Check arbitrary hints
+        for (key, regex) in &self.hints {
+            match notification.hints.get(key) {
+                Some(ref value) if regex.is_match(value) => {}
+                _ => return false,
+            }
+        }
+
+        // Check offered actions
+        if let Some(ref regex) = self.has_action {
+            if !notification
+                .actions
+                .iter()
+                .any(|(key, _)| regex.is_match(key))
+            {
                 return false;
             }
         }
 
+        // Check transient / resident flags
+        if let Some(transient) = self.transient {
+            if notification.hints.transient != transient {
+                return false;
+            }
+        }
+
+        if let Some(resident) = self.resident {
+            if notification.hints.resident != resident {
+                return false;
+            }
+        }
+
+        // Check time-of-day window
+        if !self.in_time_window(Local::now().time()) {
+            return false;
+        }
+
+        // Check combinators
+        if let Some(ref not) = self.not {
+            if not.matches(notification) {
+                return false;
+            }
+        }
+
+        if !self.any.is_empty()
+            && !self
+                .any
+                .iter()
+                .any(|alternative| alternative.matches(notification))
+        {
+            return false;
+        }
+
         true
     }
 
-    /// Check if a value matches a pattern (supports regex)
-    fn matches_pattern(pattern: &str, value: &str) -> bool {
-        // Try as regex first
-        if let Ok(regex) = Regex::new(pattern) {
-            regex.is_match(value)
-        } else {
-            // Fall back to exact match
-            pattern == value
+    /// Check if a time is within the window
+    /// (an unset start or end leaves that side of the window open)
+    fn in_time_window(&self, now: NaiveTime) -> bool {
+        let start = self.time_start.unwrap_or(NaiveTime::MIN);
+
+        match self.time_end {
+            // Same day range (e.g., 09:00 - 17:00)
+            Some(end) if start <= end => now >= start && now < end,
+            // Overnight range (e.g., 22:00 - 08:00)
+            Some(end) => now >= start || now < end,
+            // Open-ended: until midnight
+            None => now >= start,
         }
     }
 }
+
+/// Parse an urgency name from a rule
+pub(super) fn parse_urgency(value: &str, index: usize, field: &str) -> Result<Urgency, RuleError> {
+    match value.to_lowercase().as_str() {
+        "low" => Ok(Urgency::Low),
+        "normal" => Ok(Urgency::Normal),
+        "critical" => Ok(Urgency::Critical),
+        _ => Err(RuleError::Urgency {
+            index,
+            field: field.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(criteria: &str) -> Result<RuleMatcher, RuleError> {
+        let criteria: RuleCriteria = toml::from_str(criteria).unwrap();
+        RuleMatcher::compile(&criteria, 0, "criteria")
+    }
+
+    fn notification(app_name: &str, summary: &str) -> Notification {
+        Notification::new(
+            1,
+            app_name.to_string(),
+            0,
+            String::new(),
+            summary.to_string(),
+            String::new(),
+            Vec::new(),
+            Default::default(),
+            -1,
+        )
+    }
+
+    fn time(value: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(value, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn time_window_within_a_day() {
+        let matcher = compile("time_start = \"09:00\"\ntime_end = \"17:00\"").unwrap();
+
+        assert!(!matcher.in_time_window(time("08:59")));
+        assert!(matcher.in_time_window(time("09:00")));
+        assert!(matcher.in_time_window(time("16:59")));
+        assert!(!matcher.in_time_window(time("17:00")));
+    }
+
+    #[test]
+    fn time_window_across_midnight() {
+        let matcher = compile("time_start = \"22:00\"\ntime_end = \"08:00\"").unwrap();
+
+        assert!(!matcher.in_time_window(time("21:59")));
+        assert!(matcher.in_time_window(time("22:00")));
+        assert!(matcher.in_time_window(time("00:00")));
+        assert!(matcher.in_time_window(time("07:59")));
+        assert!(!matcher.in_time_window(time("08:00")));
+        assert!(!matcher.in_time_window(time("12:00")));
+    }
+
+    #[test]
+    fn time_window_open_on_one_side() {
+        let evening = compile("time_start = \"18:00\"").unwrap();
+        assert!(!evening.in_time_window(time("17:59")));
+        assert!(evening.in_time_window(time("23:59")));
+
+        let morning = compile("time_end = \"08:00\"").unwrap();
+        assert!(morning.in_time_window(time("00:00")));
+        assert!(!morning.in_time_window(time("08:00")));
+
+        let always = compile("").unwrap();
+        assert!(always.in_time_window(time("00:00")));
+        assert!(always.in_time_window(time("23:59")));
+    }
+
+    #[test]
+    fn regex_and_hint_criteria() {
+        let matcher = compile(
+            r#"
+            app_name = "^Slack$"
+            hints = { "x-kde-origin-name" = "^work" }
+            "#,
+        )
+        .unwrap();
+
+        let mut slack = notification("Slack", "Hello");
+        assert!(!matcher.matches(&slack));
+
+        slack
+            .hints
+            .extra
+            .insert("x-kde-origin-name".to_string(), "workspace".to_string());
+        assert!(matcher.matches(&slack));
+
+        let mut beta = notification("Slack Beta", "Hello");
+        beta.hints.extra = slack.hints.extra.clone();
+        assert!(!matcher.matches(&beta));
+    }
+
+    #[test]
+    fn not_and_any_combinators() {
+        let matcher = compile(
+            r#"
+            app_name = "Slack"
+            not = { summary = "^Re:" }
+            any = [{ urgency = "critical" }, { summary = "@me" }]
+            "#,
+        )
+        .unwrap();
+
+        assert!(matcher.matches(&notification("Slack", "ping @me")));
+        assert!(!matcher.matches(&notification("Slack", "Re: ping @me")));
+        assert!(!matcher.matches(&notification("Slack", "hello")));
+
+        let mut critical = notification("Slack", "hello");
+        critical.hints.urgency = Urgency::Critical;
+        assert!(matcher.matches(&critical));
+    }
+
+    #[test]
+    fn errors_name_the_field() {
+        let err = compile(r#"summary = "(unclosed""#).unwrap_err();
+        assert!(matches!(err, RuleError::Regex { ref field, .. } if field == "criteria.summary"));
+
+        let err = compile(r#"any = [{ time_end = "25:00" }]"#).unwrap_err();
+        assert!(
+            matches!(err, RuleError::Time { ref field, .. } if field == "criteria.any[0].time_end")
+        );
+
+        let err = compile(r#"not = { urgency = "urgent" }"#).unwrap_err();
+        assert!(
+            matches!(err, RuleError::Urgency { ref field, .. } if field == "criteria.not.urgency")
+        );
+    }
+}
@@ -1,7 +1,13 @@
 mod actions;
+mod exec;
 mod matcher;
+mod rewrite;
+mod set;
 
-#[allow(unused_imports)]
-pub use actions::apply_rule_actions;
+pub use actions::{apply_rule_actions, merge_rule_actions};
+pub use exec::{spawn_rule_command, DEFAULT_EXEC_TIMEOUT_MS};
 #[allow(unused_imports)]
 pub use matcher::RuleMatcher;
+#[allow(unused_imports)]
+pub use set::CompiledRule;
+pub use set::{RuleError, RuleEvaluation, RuleSet};
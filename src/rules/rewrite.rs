@@ -0,0 +1,178 @@
+use regex::Regex;
+
+use super::RuleError;
+use crate::config::{RewriteField, RewriteRule, RewriteTarget};
+use crate::notification::Notification;
+
+/// A compiled `rewrite` action
+#[derive(Debug, Clone)]
+pub struct Rewrite {
+    regex: Regex,
+    replacement: String,
+    field: RewriteField,
+    target: RewriteTarget,
+}
+
+impl Rewrite {
+    /// Compile a rewrite of the rule at `index`
+    pub fn compile(rule: &RewriteRule, index: usize, field: &str) -> Result<Self, RuleError> {
+        let regex = Regex::new(&rule.pattern).map_err(|source| RuleError::Regex {
+            index,
+            field: field.to_string(),
+            source,
+        })?;
+
+        Ok(Self {
+            regex,
+            replacement: rule.replacement.clone(),
+            field: rule.field,
+            target: rule.target,
+        })
+    }
+
+    fn applies_to(&self, target: RewriteTarget) -> bool {
+        self.target == RewriteTarget::Both || self.target == target
+    }
+
+    fn rewrite(&self, summary: &mut String, body: &mut String) {
+        if matches!(self.field, RewriteField::Summary | RewriteField::Both) {
+            *summary = self
+                .regex
+                .replace_all(summary, self.replacement.as_str())
+                .into_owned();
+        }
+        if matches!(self.field, RewriteField::Body | RewriteField::Both) {
+            *body = self
+                .regex
+                .replace_all(body, self.replacement.as_str())
+                .into_owned();
+        }
+    }
+}
+
+/// Rewrite a notification's text for display and for history
+///
+/// Popup rewrites change `summary`/`body` directly. History rewrites are
+/// applied to the original text and stored separately when they differ.
+pub fn apply_rewrites(notification: &mut Notification, rewrites: &[Rewrite]) {
+    if rewrites.is_empty() {
+        return;
+    }
+
+    let mut history_summary = notification.summary.clone();
+    let mut history_body = notification.body.clone();
+    for rewrite in rewrites
+        .iter()
+        .filter(|r| r.applies_to(RewriteTarget::History))
+    {
+        rewrite.rewrite(&mut history_summary, &mut history_body);
+    }
+
+    for rewrite in rewrites
+        .iter()
+        .filter(|r| r.applies_to(RewriteTarget::Popup))
+    {
+        rewrite.rewrite(&mut notification.summary, &mut notification.body);
+    }
+
+    if history_summary != notification.summary || history_body != notification.body {
+        notification.history_text = Some((history_summary, history_body));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(rule: &str) -> Rewrite {
+        let rule: RewriteRule = toml::from_str(rule).unwrap();
+        Rewrite::compile(&rule, 0, "actions.rewrite[0].pattern").unwrap()
+    }
+
+    fn notification(summary: &str, body: &str) -> Notification {
+        Notification::new(
+            1,
+            "app".to_string(),
+            0,
+            String::new(),
+            summary.to_string(),
+            body.to_string(),
+            Vec::new(),
+            Default::default(),
+            -1,
+        )
+    }
+
+    #[test]
+    fn replaces_with_capture_groups() {
+        let mut n = notification("3 new messages", "3 new messages");
+        let rules = [rewrite(
+            r#"
+            pattern = '(\d+) new messages'
+            replacement = "$1 unread"
+            field = "summary"
+            "#,
+        )];
+
+        apply_rewrites(&mut n, &rules);
+
+        assert_eq!(n.summary, "3 unread");
+        assert_eq!(n.body, "3 new messages");
+        assert_eq!(n.history_text, None);
+    }
+
+    #[test]
+    fn popup_only_keeps_the_original_for_history() {
+        let mut n = notification("Code: 123456", "Your login code is 123456");
+        let rules = [rewrite(
+            r#"
+            pattern = '\d{6}'
+            replacement = "******"
+            target = "popup"
+            "#,
+        )];
+
+        apply_rewrites(&mut n, &rules);
+
+        assert_eq!(n.summary, "Code: ******");
+        assert_eq!(n.body, "Your login code is ******");
+        assert_eq!(
+            n.history_summary_body(),
+            ("Code: 123456", "Your login code is 123456")
+        );
+    }
+
+    #[test]
+    fn history_only_leaves_the_popup_alone() {
+        let mut n = notification("Build failed", "token=abc123");
+        let rules = [rewrite(
+            r#"
+            pattern = 'token=\w+'
+            replacement = "token=[redacted]"
+            field = "body"
+            target = "history"
+            "#,
+        )];
+
+        apply_rewrites(&mut n, &rules);
+
+        assert_eq!(n.body, "token=abc123");
+        assert_eq!(
+            n.history_summary_body(),
+            ("Build failed", "token=[redacted]")
+        );
+    }
+
+    #[test]
+    fn rewrites_apply_in_order() {
+        let mut n = notification("a", "");
+        let rules = [
+            rewrite("pattern = 'a'\nreplacement = 'b'"),
+            rewrite("pattern = 'b'\nreplacement = 'c'"),
+        ];
+
+        apply_rewrites(&mut n, &rules);
+
+        assert_eq!(n.summary, "c");
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+
+use thiserror::Error;
+use tracing::debug;
+
+use super::matcher::{parse_urgency, RuleMatcher};
+use super::rewrite::{apply_rewrites, Rewrite};
+use super::{apply_rule_actions, merge_rule_actions};
+use crate::config::{AppRule, RuleActions};
+use crate::notification::Notification;
+
+/// Error in a `[[rules]]` entry, naming the rule index and field
+#[derive(Debug, Error)]
+pub enum RuleError {
+    #[error("rule #{index}: invalid regex in `{field}`: {source}")]
+    Regex {
+        index: usize,
+        field: String,
+        #[source]
+        source: regex::Error,
+    },
+
+    #[error("rule #{index}: invalid time {value:?} in `{field}` (expected HH:MM)")]
+    Time {
+        index: usize,
+        field: String,
+        value: String,
+    },
+
+    #[error("rule #{index}: invalid urgency {value:?} in `{field}`")]
+    Urgency {
+        index: usize,
+        field: String,
+        value: String,
+    },
+}
+
+/// A rule with its criteria compiled
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    /// Position of the rule in the config
+    pub index: usize,
+    /// Optional name for runtime toggling
+    pub name: Option<String>,
+    /// Stop evaluating later rules once this one matches
+    pub stop: bool,
+    /// Actions to apply
+    pub actions: RuleActions,
+    matcher: RuleMatcher,
+    rewrites: Vec<Rewrite>,
+}
+
+impl CompiledRule {
+    /// Check if a notification matches this rule
+    pub fn matches(&self, notification: &Notification) -> bool {
+        self.matcher.matches(notification)
+    }
+}
+
+/// The configured rules, compiled once when the config is loaded
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+    /// Names of rules disabled at runtime
+    disabled: HashSet<String>,
+}
+
+impl RuleSet {
+    /// Compile and validate rules from the config
+    pub fn compile(rules: &[AppRule]) -> Result<Self, RuleError> {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                if let Some(ref urgency) = rule.actions.urgency {
+                    parse_urgency(urgency, index, "actions.urgency")?;
+                }
+
+                let rewrites = rule
+                    .actions
+                    .rewrite
+                    .iter()
+                    .enumerate()
+                    .map(|(i, rewrite)| {
+                        let field = format!("actions.rewrite[{}].pattern", i);
+                        Rewrite::compile(rewrite, index, &field)
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                Ok(CompiledRule {
+                    index,
+                    name: rule.name.clone(),
+                    stop: rule.stop,
+                    actions: rule.actions.clone(),
+                    matcher: RuleMatcher::compile(&rule.criteria, index, "criteria")?,
+                    rewrites,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            rules,
+            disabled: HashSet::new(),
+        })
+    }
+
+    /// Carry over the runtime enabled/disabled state of another rule set
+    ///
+    /// Used on config reload so toggles made over IPC are kept, even for
+    /// names that are missing from the new config.
+    pub fn with_state_from(mut self, other: &RuleSet) -> Self {
+        self.disabled = other.disabled.clone();
+        self
+    }
+
+    /// Enable or disable every rule with the given name
+    ///
+    /// Returns `false` if no rule has that name.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        if !self.rules.iter().any(|r| r.name.as_deref() == Some(name)) {
+            return false;
+        }
+
+        if enabled {
+            self.disabled.remove(name);
+        } else {
+            self.disabled.insert(name.to_string());
+        }
+        true
+    }
+
+    /// Check if a rule is currently enabled
+    pub fn is_enabled(&self, rule: &CompiledRule) -> bool {
+        rule.name
+            .as_ref()
+            .is_none_or(|name| !self.disabled.contains(name))
+    }
+
+    /// All compiled rules, in config order
+    pub fn rules(&self) -> &[CompiledRule] {
+        &self.rules
+    }
+
+    /// Find all matching rules for a notification, in config order
+    ///
+    /// Disabled rules are skipped. Evaluation stops after the first
+    /// matching rule marked `final`.
+    pub fn matching_rules(&self, notification: &Notification) -> Vec<&CompiledRule> {
+        let mut matched = Vec::new();
+        for rule in &self.rules {
+            if self.is_enabled(rule) && rule.matches(notification) {
+                debug!(
+                    "Notification from '{}' matched rule #{}",
+                    notification.app_name, rule.index
+                );
+                matched.push(rule);
+                if rule.stop {
+                    break;
+                }
+            }
+        }
+        matched
+    }
+
+    /// Merge the actions of all matching rules (later rules win)
+    pub fn evaluate(&self, notification: &Notification) -> RuleEvaluation {
+        let mut evaluation = RuleEvaluation::default();
+        for rule in self.matching_rules(notification) {
+            evaluation.matched.push(rule.index);
+            merge_rule_actions(&mut evaluation.actions, &rule.actions);
+            evaluation.rewrites.extend(rule.rewrites.iter().cloned());
+        }
+        evaluation
+    }
+
+    /// Number of compiled rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Check if there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Outcome of running a notification through the rule set
+#[derive(Debug, Clone, Default)]
+pub struct RuleEvaluation {
+    /// Indices of the matching rules, in evaluation order
+    pub matched: Vec<usize>,
+    /// Merged actions of the matching rules
+    pub actions: RuleActions,
+    rewrites: Vec<Rewrite>,
+}
+
+impl RuleEvaluation {
+    /// Check if any rule matched
+    pub fn is_match(&self) -> bool {
+        !self.matched.is_empty()
+    }
+
+    /// Apply the merged actions and text rewrites to a notification
+    pub fn apply(&self, notification: &mut Notification) {
+        apply_rule_actions(notification, &self.actions);
+        apply_rewrites(notification, &self.rewrites);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::config::ConfigLoader;
+
+    /// Load rules the way the daemon does, from a config file
+    fn load(config: &str) -> anyhow::Result<RuleSet> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(config.as_bytes()).unwrap();
+        ConfigLoader::load_from_path(&file.path().to_path_buf()).map(|(_, rules)| rules)
+    }
+
+    fn notification(app_name: &str) -> Notification {
+        Notification::new(
+            1,
+            app_name.to_string(),
+            0,
+            String::new(),
+            "Hello".to_string(),
+            String::new(),
+            Vec::new(),
+            Default::default(),
+            -1,
+        )
+    }
+
+    const RULES: &str = r#"
+        [[rules]]
+        name = "slow"
+        criteria = { app_name = "Slack" }
+        actions = { timeout = 1000, css_class = "chat" }
+
+        [[rules]]
+        name = "quiet"
+        final = true
+        criteria = { app_name = "Slack" }
+        actions = { timeout = 2000 }
+
+        [[rules]]
+        criteria = { app_name = ".*" }
+        actions = { urgency = "low" }
+    "#;
+
+    #[test]
+    fn later_rules_win_until_a_final_one() {
+        let rules = load(RULES).unwrap();
+
+        let evaluation = rules.evaluate(&notification("Slack"));
+        assert_eq!(evaluation.matched, [0, 1]);
+        assert_eq!(evaluation.actions.timeout, Some(2000));
+        assert_eq!(evaluation.actions.css_class.as_deref(), Some("chat"));
+        assert_eq!(evaluation.actions.urgency, None);
+
+        let evaluation = rules.evaluate(&notification("Firefox"));
+        assert_eq!(evaluation.matched, [2]);
+        assert_eq!(evaluation.actions.urgency.as_deref(), Some("low"));
+    }
+
+    #[test]
+    fn disabled_rules_are_skipped_and_survive_a_reload() {
+        let mut rules = load(RULES).unwrap();
+        assert!(rules.set_enabled("quiet", false));
+        assert!(!rules.set_enabled("missing", false));
+
+        // Without the final rule, the catch-all matches too
+        let evaluation = rules.evaluate(&notification("Slack"));
+        assert_eq!(evaluation.matched, [0, 2]);
+        assert_eq!(evaluation.actions.timeout, Some(1000));
+
+        let reloaded = load(RULES).unwrap().with_state_from(&rules);
+        assert_eq!(reloaded.evaluate(&notification("Slack")).matched, [0, 2]);
+    }
+
+    #[test]
+    fn invalid_rules_fail_the_load() {
+        let err = load(
+            r#"
+            [[rules]]
+            criteria = { app_name = "Slack" }
+
+            [[rules]]
+            criteria = { app_name = "Slack" }
+            actions = { rewrite = [{ pattern = "(" }] }
+            "#,
+        )
+        .unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("rule #1"), "{}", message);
+        assert!(
+            message.contains("actions.rewrite[0].pattern"),
+            "{}",
+            message
+        );
+
+        let err = load(
+            r#"
+            [[rules]]
+            criteria = { app_name = "Slack" }
+            actions = { urgency = "urgent" }
+            "#,
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("rule #0: invalid urgency"));
+    }
+}
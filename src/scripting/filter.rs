@@ -0,0 +1,292 @@
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use serde::de::value::{Error as DeError, StrDeserializer};
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::config::ScriptConfig;
+use crate::notification::{Notification, Urgency};
+
+thread_local! {
+    /// Deadline of the script running on this thread
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Name of the notification map in the script's scope
+const VARIABLE: &str = "notification";
+
+/// Error loading the filter script
+#[derive(Debug, Error)]
+pub enum ScriptError {
+    #[error("failed to read script {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("failed to compile script {path:?}: {source}")]
+    Compile {
+        path: PathBuf,
+        #[source]
+        source: rhai::ParseError,
+    },
+}
+
+/// User script run on every notification before it is stored
+///
+/// The script sees the notification as a `notification` object map and can
+/// change its text and presentation, or drop it with
+/// `notification.display = "drop"`. It runs in a Rhai engine without access
+/// to files, modules or `eval`, and is stopped once it exceeds the configured
+/// time limit. Errors are logged and leave the notification unchanged.
+pub struct ScriptFilter {
+    path: PathBuf,
+    engine: Engine,
+    ast: AST,
+    timeout: Duration,
+}
+
+impl ScriptFilter {
+    /// Load the script from `[script]`, or `None` if no path is set
+    pub fn load(config: &ScriptConfig) -> Result<Option<Self>, ScriptError> {
+        let Some(ref path) = config.path else {
+            return Ok(None);
+        };
+        let path = PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).as_ref());
+
+        let source = std::fs::read_to_string(&path).map_err(|source| ScriptError::Read {
+            path: path.clone(),
+            source,
+        })?;
+
+        let engine = Self::sandboxed_engine();
+        let ast = engine
+            .compile(source)
+            .map_err(|source| ScriptError::Compile {
+                path: path.clone(),
+                source,
+            })?;
+
+        info!("Loaded notification script from {:?}", path);
+        Ok(Some(Self {
+            path,
+            engine,
+            ast,
+            timeout: Duration::from_millis(config.timeout),
+        }))
+    }
+
+    /// Build an engine with no I/O and bounded resource usage
+    fn sandboxed_engine() -> Engine {
+        let mut engine = Engine::new();
+
+        engine
+            .set_module_resolver(DummyModuleResolver::new())
+            .disable_symbol("eval")
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(64 * 1024)
+            .set_max_array_size(1024)
+            .set_max_map_size(1024);
+
+        // Checking the clock on every operation would be wasteful
+        engine.on_progress(|operations| {
+            if operations % 256 != 0 {
+                return None;
+            }
+            let expired = DEADLINE.with(|d| d.get().is_some_and(|d| Instant::now() > d));
+            expired.then_some(Dynamic::UNIT)
+        });
+
+        engine.on_print(|text| info!("script: {}", text));
+        engine.on_debug(|text, _, pos| debug!("script ({}): {}", pos, text));
+
+        engine
+    }
+
+    /// Run the script on a notification
+    pub fn apply(&self, notification: &mut Notification) {
+        let mut scope = Scope::new();
+        scope.push(VARIABLE, to_map(notification));
+
+        DEADLINE.with(|d| d.set(Some(Instant::now() + self.timeout)));
+        let result = self.engine.run_ast_with_scope(&mut scope, &self.ast);
+        DEADLINE.with(|d| d.set(None));
+
+        match result {
+            Ok(()) => {
+                if let Some(map) = scope.get_value::<Map>(VARIABLE) {
+                    from_map(&map, notification);
+                } else {
+                    warn!(
+                        "Script {:?} replaced `{}` with a non-map value",
+                        self.path, VARIABLE
+                    );
+                }
+            }
+            Err(e) if matches!(*e, EvalAltResult::ErrorTerminated(..)) => {
+                warn!(
+                    "Script {:?} exceeded its {} ms time limit on notification from '{}'",
+                    self.path,
+                    self.timeout.as_millis(),
+                    notification.app_name
+                );
+            }
+            Err(e) => {
+                warn!(
+                    "Script {:?} failed on notification from '{}': {}",
+                    self.path, notification.app_name, e
+                );
+            }
+        }
+    }
+}
+
+/// Expose a notification to the script
+fn to_map(notification: &Notification) -> Map {
+    fn opt(value: &Option<String>) -> Dynamic {
+        value.clone().map(Dynamic::from).unwrap_or(Dynamic::UNIT)
+    }
+
+    let actions: Array = notification
+        .actions
+        .iter()
+        .map(|(key, _)| Dynamic::from(key.clone()))
+        .collect();
+
+    let mut hints = Map::new();
+    for (key, value) in &notification.hints.extra {
+        hints.insert(key.as_str().into(), value.clone().into());
+    }
+    for key in [
+        "category",
+        "desktop-entry",
+        "image-path",
+        "sound-file",
+        "sound-name",
+    ] {
+        if let Some(value) = notification.hints.get(key) {
+            hints.insert(key.into(), value.into());
+        }
+    }
+
+    let mut map = Map::new();
+    map.insert("app_name".into(), notification.app_name.clone().into());
+    map.insert("summary".into(), notification.summary.clone().into());
+    map.insert("body".into(), notification.body.clone().into());
+    map.insert(
+        "urgency".into(),
+        notification.hints.urgency.to_string().into(),
+    );
+    map.insert("category".into(), opt(&notification.hints.category));
+    map.insert(
+        "desktop_entry".into(),
+        opt(&notification.hints.desktop_entry),
+    );
+    map.insert("transient".into(), notification.hints.transient.into());
+    map.insert("resident".into(), notification.hints.resident.into());
+    map.insert("actions".into(), actions.into());
+    map.insert("hints".into(), hints.into());
+    map.insert(
+        "timeout".into(),
+        (notification.expire_timeout as i64).into(),
+    );
+    map.insert("display".into(), enum_name(&notification.display).into());
+    map.insert(
+        "anchor".into(),
+        notification
+            .anchor
+            .map(|a| Dynamic::from(enum_name(&a)))
+            .unwrap_or(Dynamic::UNIT),
+    );
+    map.insert("skip_history".into(), notification.skip_history.into());
+    map.insert("skip_sound".into(), notification.skip_sound.into());
+    map.insert("css_class".into(), opt(&notification.css_class));
+    map.insert("icon".into(), opt(&notification.icon));
+    map.insert("app_display_name".into(), opt(&notification.display_name));
+    map.insert("sound".into(), opt(&notification.sound));
+    map.insert("group".into(), opt(&notification.group));
+    map
+}
+
+/// Copy the fields the script may change back onto the notification
+///
+/// Values of the wrong type are logged and ignored.
+fn from_map(map: &Map, notification: &mut Notification) {
+    fn get<T: Clone + 'static>(map: &Map, key: &str) -> Option<T> {
+        let value = map.get(key)?;
+        let cast = value.clone().try_cast::<T>();
+        if cast.is_none() && !value.is_unit() {
+            warn!("Script set `{}` to an invalid {}", key, value.type_name());
+        }
+        cast
+    }
+
+    fn get_string(map: &Map, key: &str) -> Option<String> {
+        get::<rhai::ImmutableString>(map, key).map(|s| s.to_string())
+    }
+
+    if let Some(summary) = get_string(map, "summary") {
+        notification.summary = summary;
+    }
+    if let Some(body) = get_string(map, "body") {
+        notification.body = body;
+    }
+    if let Some(urgency) = get_string(map, "urgency") {
+        match urgency.to_lowercase().as_str() {
+            "low" => notification.hints.urgency = Urgency::Low,
+            "normal" => notification.hints.urgency = Urgency::Normal,
+            "critical" => notification.hints.urgency = Urgency::Critical,
+            other => warn!("Script set an invalid urgency {:?}", other),
+        }
+    }
+    if let Some(timeout) = get::<i64>(map, "timeout") {
+        notification.expire_timeout = timeout.clamp(-1, i32::MAX as i64) as i32;
+    }
+    if let Some(value) = get_string(map, "display") {
+        match parse_enum(&value) {
+            Some(mode) => notification.display = mode,
+            None => warn!("Script set an invalid display {:?}", value),
+        }
+    }
+    if let Some(value) = get_string(map, "anchor") {
+        match parse_enum(&value) {
+            Some(anchor) => notification.anchor = Some(anchor),
+            None => warn!("Script set an invalid anchor {:?}", value),
+        }
+    }
+    if let Some(skip_history) = get::<bool>(map, "skip_history") {
+        notification.skip_history = skip_history;
+    }
+    if let Some(skip_sound) = get::<bool>(map, "skip_sound") {
+        notification.skip_sound = skip_sound;
+    }
+
+    // Presentation overrides can also be cleared by setting them to ()
+    notification.css_class = get_string(map, "css_class");
+    notification.icon = get_string(map, "icon");
+    notification.display_name = get_string(map, "app_display_name");
+    notification.sound = get_string(map, "sound");
+    notification.group = get_string(map, "group");
+}
+
+/// Config name of a unit enum variant (e.g. `history-only`)
+fn enum_name<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Parse a unit enum variant from its config name, honoring aliases
+fn parse_enum<'de, T: Deserialize<'de>>(value: &'de str) -> Option<T> {
+    let deserializer: StrDeserializer<'de, DeError> = value.into_deserializer();
+    T::deserialize(deserializer).ok()
+}
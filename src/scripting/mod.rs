@@ -0,0 +1,6 @@
+#[cfg(feature = "scripting")]
+mod filter;
+
+#[cfg(feature = "scripting")]
+#[allow(unused_imports)]
+pub use filter::{ScriptError, ScriptFilter};
@@ -8,7 +8,7 @@ use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
 use tracing::{debug, info, warn};
 
 use crate::config::Config;
-use crate::notification::Urgency;
+use crate::notification::{Notification, Urgency};
 
 /// Sound player with urgency-based sound support
 pub struct SoundPlayer {
@@ -63,6 +63,48 @@ impl SoundPlayer {
         Ok(())
     }
 
+    /// Play sound for a notification, honoring its sound hints and rule flags
+    pub fn play_for_notification(
+        &self,
+        notification: &Notification,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if notification.skip_sound || notification.hints.suppress_sound {
+            debug!("Sound skipped for notification {}", notification.id);
+            return Ok(());
+        }
+
+        self.play_reminder(notification)
+    }
+
+    /// Play the sound of a notification even if it arrived muted
+    ///
+    /// For escalation reminders, which were asked for explicitly. Sound still
+    /// has to be enabled in `[sound]`.
+    pub fn play_reminder(
+        &self,
+        notification: &Notification,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.config.read().sound.enabled {
+            debug!("Sound is disabled");
+            return Ok(());
+        }
+
+        if let Some(ref sound) = notification.sound {
+            // Rule override: a path or a sound theme name
+            if sound.contains('/') {
+                self.play_file(sound)
+            } else {
+                self.play_sound_name(sound)
+            }
+        } else if let Some(ref file) = notification.hints.sound_file {
+            self.play_file(file)
+        } else if let Some(ref name) = notification.hints.sound_name {
+            self.play_sound_name(name)
+        } else {
+            self.play_for_urgency(notification.hints.urgency)
+        }
+    }
+
     /// Play a specific sound file
     pub fn play_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
         let path = path.as_ref();
@@ -152,6 +194,22 @@ impl SoundService {
         }
     }
 
+    pub fn play_for_notification(&self, notification: &Notification) {
+        if let Some(ref player) = self.player {
+            if let Err(e) = player.play_for_notification(notification) {
+                warn!("Failed to play sound: {}", e);
+            }
+        }
+    }
+
+    pub fn play_reminder(&self, notification: &Notification) {
+        if let Some(ref player) = self.player {
+            if let Err(e) = player.play_reminder(notification) {
+                warn!("Failed to play sound: {}", e);
+            }
+        }
+    }
+
     pub fn play_file<P: AsRef<Path>>(&self, path: P) {
         if let Some(ref player) = self.player {
             if let Err(e) = player.play_file(path) {
@@ -1,5 +1,5 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::Arc;
 
@@ -8,11 +8,16 @@ use gtk4::{gio, Application};
 use parking_lot::RwLock;
 use tracing::{debug, info};
 
-use crate::config::Config;
+#[cfg(feature = "sound")]
+use crate::config::EscalationAction;
+use crate::config::{Anchor, Config};
 use crate::history::HistoryStore;
-use crate::notification::{ActionEvent, Notification, UiEvent};
+use crate::notification::{ActionEvent, Notification, NotificationEvent, UiEvent};
+#[cfg(feature = "sound")]
+use crate::sound::SoundService;
 
 use super::notification_center::NotificationCenter;
+use super::overflow::OverflowIndicator;
 use super::style::StyleManager;
 use super::window::NotificationWindow;
 
@@ -23,9 +28,12 @@ pub struct SwaynotiApp {
     app: Application,
     config: Arc<RwLock<Config>>,
     style_manager: Rc<StyleManager>,
+    /// Popup windows by the ID of the notification that opened them
     windows: Rc<RefCell<HashMap<u32, NotificationWindow>>>,
     action_sender: Sender<ActionEvent>,
     history_store: Option<Arc<HistoryStore>>,
+    /// Lifecycle events from the notification manager
+    events: Receiver<NotificationEvent>,
 }
 
 impl SwaynotiApp {
@@ -34,6 +42,7 @@ impl SwaynotiApp {
         config: Arc<RwLock<Config>>,
         action_sender: Sender<ActionEvent>,
         history_store: Option<Arc<HistoryStore>>,
+        events: Receiver<NotificationEvent>,
     ) -> Self {
         let app = Application::builder()
             .application_id(APP_ID)
@@ -50,6 +59,7 @@ impl SwaynotiApp {
             windows,
             action_sender,
             history_store,
+            events,
         }
     }
 
@@ -65,6 +75,17 @@ impl SwaynotiApp {
         let action_sender = self.action_sender.clone();
         let history_store = self.history_store.clone();
 
+        // Notification center, created the first time it is opened
+        let notification_center: Rc<RefCell<Option<NotificationCenter>>> =
+            Rc::new(RefCell::new(None));
+
+        // React to lifecycle events on the GLib main context too
+        glib::MainContext::default().spawn_local(Self::handle_manager_events(
+            config.clone(),
+            notification_center.clone(),
+            self.events,
+        ));
+
         // Spawn UI event handler on GLib main context
         glib::MainContext::default().spawn_local(async move {
             Self::handle_ui_events(
@@ -73,6 +94,7 @@ impl SwaynotiApp {
                 windows,
                 action_sender,
                 history_store,
+                notification_center,
                 ui_receiver,
             )
             .await;
@@ -91,13 +113,13 @@ impl SwaynotiApp {
         windows: Rc<RefCell<HashMap<u32, NotificationWindow>>>,
         action_sender: Sender<ActionEvent>,
         history_store: Option<Arc<HistoryStore>>,
+        notification_center: Rc<RefCell<Option<NotificationCenter>>>,
         receiver: Receiver<UiEvent>,
     ) {
         info!("UI event handler started");
 
-        // Create notification center (lazily initialized)
-        let notification_center: Rc<RefCell<Option<NotificationCenter>>> =
-            Rc::new(RefCell::new(None));
+        // "+N more" popup, present while notifications are pending
+        let mut overflow: Option<OverflowIndicator> = None;
 
         while let Ok(event) = receiver.recv().await {
             match event {
@@ -107,11 +129,14 @@ impl SwaynotiApp {
                 UiEvent::Update(id, notification) => {
                     Self::update_notification(&config, &windows, id, notification);
                 }
-                UiEvent::Close(id) => {
+                UiEvent::Close(id) | UiEvent::Hide(id) => {
                     Self::close_notification(&windows, id);
                 }
-                UiEvent::Reposition => {
-                    Self::reposition_all(&config, &windows);
+                UiEvent::Reposition(order) => {
+                    Self::reposition_all(&config, &windows, &order, &overflow);
+                }
+                UiEvent::Pending(count) => {
+                    Self::update_overflow(&app, &config, &windows, &mut overflow, count);
                 }
                 UiEvent::ShowCenter => {
                     Self::ensure_notification_center(
@@ -148,6 +173,46 @@ impl SwaynotiApp {
         info!("UI event handler stopped");
     }
 
+    /// Play sounds and keep the notification center current as the
+    /// manager reports lifecycle events
+    async fn handle_manager_events(
+        config: Arc<RwLock<Config>>,
+        notification_center: Rc<RefCell<Option<NotificationCenter>>>,
+        events: Receiver<NotificationEvent>,
+    ) {
+        // Audio output has to stay on this thread
+        #[cfg(feature = "sound")]
+        let sound = SoundService::new(config.clone());
+        #[cfg(not(feature = "sound"))]
+        let _ = config;
+
+        while let Ok(event) = events.recv().await {
+            match event {
+                #[cfg(feature = "sound")]
+                NotificationEvent::Shown(notification) => {
+                    sound.play_for_notification(&notification);
+                }
+                // Muted notifications (restored ones, `skip_sound`) remind too
+                #[cfg(feature = "sound")]
+                NotificationEvent::Escalated {
+                    notification,
+                    action: EscalationAction::Sound,
+                    ..
+                } => {
+                    sound.play_reminder(&notification);
+                }
+                NotificationEvent::HistoryChanged => {
+                    if let Some(ref mut center) = *notification_center.borrow_mut() {
+                        if center.is_visible() {
+                            center.refresh();
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Ensure notification center is created
     fn ensure_notification_center(
         app: &Application,
@@ -176,18 +241,37 @@ impl SwaynotiApp {
         action_sender: &Sender<ActionEvent>,
         notification: Notification,
     ) {
+        // The manager only sends notifications that have a free slot
         let id = notification.id;
         let config_read = config.read();
 
-        // Calculate index for stacking
-        let index = windows.borrow().len();
+        // Calculate index for stacking (per anchor, since rules may move popups)
+        let anchor = notification
+            .anchor
+            .unwrap_or(config_read.positioning.anchor);
 
-        // Check max visible limit
-        if index >= config_read.general.max_visible as usize {
-            debug!("Max visible notifications reached, not showing {}", id);
-            return;
+        // Join the popup of the same group, if grouping is on
+        if config_read.general.group_popups {
+            let mut windows_mut = windows.borrow_mut();
+            if let Some(window) = windows_mut.values_mut().find(|window| {
+                window.anchor() == anchor && window.group() == notification.group_key()
+            }) {
+                window.push(&notification, &config_read);
+                info!(
+                    "Stacked notification {} onto group '{}'",
+                    id,
+                    window.group()
+                );
+                return;
+            }
         }
 
+        let index = windows
+            .borrow()
+            .values()
+            .filter(|window| window.anchor() == anchor)
+            .count();
+
         let window = NotificationWindow::new(
             app,
             &notification,
@@ -214,41 +298,107 @@ impl SwaynotiApp {
         notification: Notification,
     ) {
         let windows_ref = windows.borrow();
-        if let Some(window) = windows_ref.get(&id) {
+        if let Some(window) = windows_ref.values().find(|window| window.contains(id)) {
             let config_read = config.read();
             window.update(&notification, &config_read);
             debug!("Updated notification {}", id);
         }
     }
 
-    /// Close a notification
+    /// Close a notification, and its window once nothing is left in it
     fn close_notification(windows: &Rc<RefCell<HashMap<u32, NotificationWindow>>>, id: u32) {
-        let window = windows.borrow_mut().remove(&id);
-        if let Some(window) = window {
-            window.close();
-            info!(
-                "Closed notification {} (remaining: {})",
-                id,
-                windows.borrow().len()
-            );
+        let mut windows_mut = windows.borrow_mut();
+        let Some(key) = windows_mut
+            .iter()
+            .find(|(_, window)| window.contains(id))
+            .map(|(&key, _)| key)
+        else {
+            return;
+        };
+
+        let empty = windows_mut
+            .get_mut(&key)
+            .is_some_and(|window| window.remove(id));
+        if empty {
+            if let Some(window) = windows_mut.remove(&key) {
+                window.close();
+            }
         }
+        info!(
+            "Closed notification {} (remaining windows: {})",
+            id,
+            windows_mut.len()
+        );
     }
 
-    /// Reposition all notification windows
+    /// Reposition all notification windows in display order
     fn reposition_all(
         config: &Arc<RwLock<Config>>,
         windows: &Rc<RefCell<HashMap<u32, NotificationWindow>>>,
+        order: &[u32],
+        overflow: &Option<OverflowIndicator>,
     ) {
         let config_read = config.read();
         let windows_ref = windows.borrow();
+        let mut indices: HashMap<Anchor, usize> = HashMap::new();
 
-        for (index, (_, window)) in windows_ref.iter().enumerate() {
-            window.update_position(&config_read, index);
+        // A stack goes where the first of its notifications is in the order
+        let mut placed = HashSet::new();
+        for id in order {
+            let Some((&key, window)) = windows_ref.iter().find(|(_, window)| window.contains(*id))
+            else {
+                continue;
+            };
+            if !placed.insert(key) {
+                continue;
+            }
+            let index = indices.entry(window.anchor()).or_default();
+            window.update_position(&config_read, *index);
+            *index += 1;
+        }
+
+        // The overflow indicator sits below the default stack
+        if let Some(indicator) = overflow {
+            let index = indices
+                .get(&config_read.positioning.anchor)
+                .copied()
+                .unwrap_or(0);
+            indicator.update_position(&config_read, index);
         }
 
         debug!("Repositioned {} notifications", windows_ref.len());
     }
 
+    /// Show, update or remove the "+N more" indicator
+    fn update_overflow(
+        app: &Application,
+        config: &Arc<RwLock<Config>>,
+        windows: &Rc<RefCell<HashMap<u32, NotificationWindow>>>,
+        overflow: &mut Option<OverflowIndicator>,
+        count: usize,
+    ) {
+        if count == 0 {
+            if let Some(indicator) = overflow.take() {
+                indicator.close();
+            }
+            return;
+        }
+
+        let indicator = overflow.get_or_insert_with(|| {
+            let config_read = config.read();
+            let anchor = config_read.positioning.anchor;
+            let index = windows
+                .borrow()
+                .values()
+                .filter(|window| window.anchor() == anchor)
+                .count();
+            let indicator = OverflowIndicator::new(app, &config_read, index);
+            indicator.show();
+            indicator
+        });
+        indicator.set_count(count);
+    }
+
     /// Get the GTK application
     pub fn application(&self) -> &Application {
         &self.app
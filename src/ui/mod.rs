@@ -2,6 +2,7 @@ mod app;
 mod media_widget;
 mod notification_center;
 mod notification_widget;
+mod overflow;
 mod style;
 mod window;
 
@@ -12,6 +13,8 @@ pub use media_widget::MediaWidget;
 pub use notification_center::NotificationCenter;
 pub use notification_widget::NotificationWidget;
 #[allow(unused_imports)]
+pub use overflow::OverflowIndicator;
+#[allow(unused_imports)]
 pub use style::StyleManager;
 #[allow(unused_imports)]
 pub use window::NotificationWindow;
@@ -341,8 +341,11 @@ impl NotificationCenter {
             entry_box.append(&body);
         }
 
-        // Timestamp
-        let time_ago = Self::format_time_ago(&entry.timestamp);
+        // Timestamp, with the repeat count of merged duplicates
+        let mut time_ago = Self::format_time_ago(&entry.timestamp);
+        if entry.repeat_count > 1 {
+            time_ago = format!("{} · ×{}", time_ago, entry.repeat_count);
+        }
         let time_label = Label::new(Some(&time_ago));
         time_label.add_css_class("entry-time");
         time_label.set_halign(Align::Start);
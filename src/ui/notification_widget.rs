@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+
 use async_channel::Sender;
 use gtk4::prelude::*;
 use gtk4::{Align, Box as GtkBox, Button, Entry, Image, Label, Orientation, ProgressBar, Widget};
@@ -10,6 +12,10 @@ use crate::notification::{ActionEvent, Notification};
 pub struct NotificationWidget {
     container: GtkBox,
     notification_id: u32,
+    /// CSS classes added by a matching rule
+    rule_classes: RefCell<Vec<String>>,
+    /// "×N" counter shown when identical notifications were merged
+    repeat_label: Label,
 }
 
 impl NotificationWidget {
@@ -21,6 +27,10 @@ impl NotificationWidget {
         let container = GtkBox::new(Orientation::Horizontal, 12);
         container.add_css_class("notification");
         container.add_css_class(notification.hints.urgency.css_class());
+        let rule_classes = Self::rule_classes(notification);
+        for class in &rule_classes {
+            container.add_css_class(class);
+        }
 
         // Icon (left side)
         if let Some(icon) = Self::create_icon(notification, config) {
@@ -32,21 +42,30 @@ impl NotificationWidget {
         content_box.set_hexpand(true);
 
         // App name (optional)
-        if config.appearance.show_app_name && !notification.app_name.is_empty() {
-            let app_label = Label::new(Some(&notification.app_name));
+        let app_name = notification.display_app_name();
+        if config.appearance.show_app_name && !app_name.is_empty() {
+            let app_label = Label::new(Some(app_name));
             app_label.add_css_class("app-name");
             app_label.set_halign(Align::Start);
             app_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
             content_box.append(&app_label);
         }
 
-        // Summary (title)
+        // Summary (title) with the repeat counter next to it
+        let summary_box = GtkBox::new(Orientation::Horizontal, 6);
         let summary_label = Label::new(Some(&notification.summary));
         summary_label.add_css_class("summary");
         summary_label.set_halign(Align::Start);
         summary_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
         summary_label.set_max_width_chars(40);
-        content_box.append(&summary_label);
+        summary_box.append(&summary_label);
+
+        let repeat_label = Label::new(None);
+        repeat_label.add_css_class("repeat-count");
+        repeat_label.set_halign(Align::Start);
+        Self::set_repeat_count(&repeat_label, notification.repeat_count);
+        summary_box.append(&repeat_label);
+        content_box.append(&summary_box);
 
         // Body
         if !notification.body.is_empty() {
@@ -112,14 +131,36 @@ impl NotificationWidget {
         Self {
             container,
             notification_id: notification.id,
+            rule_classes: RefCell::new(rule_classes),
+            repeat_label,
         }
     }
 
+    /// Show "×N" for merged repeats, nothing for a single notification
+    fn set_repeat_count(label: &Label, count: u32) {
+        label.set_text(&format!("×{}", count));
+        label.set_visible(count > 1);
+    }
+
+    /// CSS classes requested by a rule (whitespace separated)
+    fn rule_classes(notification: &Notification) -> Vec<String> {
+        notification
+            .css_class
+            .as_deref()
+            .map(|classes| classes.split_whitespace().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
     /// Create the notification icon
     fn create_icon(notification: &Notification, config: &Config) -> Option<Widget> {
         let size = config.appearance.icon_size as i32;
 
-        // Try image data from hints first
+        // A rule override beats anything the app sent
+        if let Some(ref icon) = notification.icon {
+            return Some(Self::icon_from_spec(icon, size));
+        }
+
+        // Try image data from hints
         if let Some(ref image_data) = notification.hints.image_data {
             // Create pixbuf from raw data
             let pixbuf = gdk_pixbuf::Pixbuf::from_bytes(
@@ -150,28 +191,30 @@ impl NotificationWidget {
 
         // Try app_icon
         if !notification.app_icon.is_empty() {
-            let icon = &notification.app_icon;
-
-            // Check if it's a file path
-            if icon.starts_with('/') || icon.starts_with("file://") {
-                let path = icon.strip_prefix("file://").unwrap_or(icon);
-                let image = Image::from_file(path);
-                image.set_pixel_size(size);
-                image.add_css_class("icon");
-                return Some(image.upcast());
-            }
-
-            // Treat as icon name
-            let image = Image::from_icon_name(icon);
-            image.set_pixel_size(size);
-            image.add_css_class("icon");
-            return Some(image.upcast());
+            return Some(Self::icon_from_spec(&notification.app_icon, size));
         }
 
         // No icon
         None
     }
 
+    /// Create an icon from a file path, `file://` URI or icon name
+    fn icon_from_spec(icon: &str, size: i32) -> Widget {
+        // Check if it's a file path
+        let image = if icon.starts_with('/') || icon.starts_with("file://") {
+            let path = icon.strip_prefix("file://").unwrap_or(icon);
+            Image::from_file(path)
+        } else if icon.starts_with('~') {
+            Image::from_file(&*shellexpand::tilde(icon))
+        } else {
+            // Treat as icon name
+            Image::from_icon_name(icon)
+        };
+        image.set_pixel_size(size);
+        image.add_css_class("icon");
+        image.upcast()
+    }
+
     /// Create action buttons
     fn create_actions(notification: &Notification, action_sender: Sender<ActionEvent>) -> GtkBox {
         let actions_box = GtkBox::new(Orientation::Horizontal, 6);
@@ -295,6 +338,17 @@ impl NotificationWidget {
         self.container.remove_css_class("critical");
         self.container
             .add_css_class(notification.hints.urgency.css_class());
+
+        let mut rule_classes = self.rule_classes.borrow_mut();
+        for class in rule_classes.iter() {
+            self.container.remove_css_class(class);
+        }
+        *rule_classes = Self::rule_classes(notification);
+        for class in rule_classes.iter() {
+            self.container.add_css_class(class);
+        }
+
+        Self::set_repeat_count(&self.repeat_label, notification.repeat_count);
     }
 
     /// Get the notification ID
@@ -0,0 +1,62 @@
+use gtk4::prelude::*;
+use gtk4::{Application, Label, Window};
+use gtk4_layer_shell::{KeyboardMode, LayerShell};
+use tracing::debug;
+
+use crate::config::Config;
+
+use super::NotificationWindow;
+
+/// Small popup below the stack saying how many notifications are pending
+pub struct OverflowIndicator {
+    window: Window,
+    label: Label,
+}
+
+impl OverflowIndicator {
+    /// Create the indicator on the default anchor
+    pub fn new(app: &Application, config: &Config, index: usize) -> Self {
+        let window = Window::builder()
+            .application(app)
+            .decorated(false)
+            .resizable(false)
+            .build();
+
+        window.init_layer_shell();
+        window.set_layer(NotificationWindow::layer(config));
+        NotificationWindow::apply_anchors(&window, &config.positioning.anchor);
+        NotificationWindow::apply_margins(&window, config, config.positioning.anchor, index);
+        window.set_keyboard_mode(KeyboardMode::None);
+        window.set_exclusive_zone(0);
+        window.set_default_width(config.appearance.width as i32);
+        window.add_css_class("notification-window");
+
+        let label = Label::new(None);
+        label.add_css_class("notification");
+        label.add_css_class("overflow");
+        window.set_child(Some(&label));
+
+        Self { window, label }
+    }
+
+    /// Set the number of pending notifications
+    pub fn set_count(&self, count: usize) {
+        self.label.set_text(&format!("+{} more", count));
+        debug!("Overflow indicator shows {} pending", count);
+    }
+
+    /// Move the indicator below the popups on its anchor
+    pub fn update_position(&self, config: &Config, index: usize) {
+        NotificationWindow::apply_margins(&self.window, config, config.positioning.anchor, index);
+    }
+
+    /// Show the window
+    pub fn show(&self) {
+        self.window.present();
+    }
+
+    /// Hide and destroy the window
+    pub fn close(&self) {
+        self.window.close();
+    }
+}
@@ -1,8 +1,11 @@
 use std::cell::Cell;
+use std::rc::Rc;
 
 use async_channel::Sender;
 use gtk4::prelude::*;
-use gtk4::{Application, Window};
+use gtk4::{
+    Application, Box as GtkBox, Button, Orientation, Revealer, RevealerTransitionType, Window,
+};
 use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
 use tracing::{debug, info};
 
@@ -11,12 +14,28 @@ use crate::notification::{ActionEvent, Notification};
 
 use super::NotificationWidget;
 
-/// A layer-shell window displaying a single notification
+/// A layer-shell window displaying a notification, or a stack of them
+///
+/// With `group_popups` enabled, later notifications of the same group are
+/// pushed onto the window: the latest one is shown with an "N more" button
+/// that expands the older ones.
 pub struct NotificationWindow {
     window: Window,
+    /// ID of the notification that opened the window
     notification_id: u32,
     app_name: String,
-    widget: NotificationWidget,
+    group: String,
+    anchor: Anchor,
+    /// Notifications in the stack, oldest first; the last one is on top
+    members: Vec<NotificationWidget>,
+    /// Holds the latest notification
+    latest: GtkBox,
+    /// Holds the older notifications, newest first
+    others: GtkBox,
+    revealer: Revealer,
+    toggle: Button,
+    /// Number of notifications behind the latest, read by the toggle button
+    hidden_count: Rc<Cell<usize>>,
     action_sender: Sender<ActionEvent>,
 }
 
@@ -39,19 +58,14 @@ impl NotificationWindow {
         window.init_layer_shell();
 
         // Set the layer
-        let layer = match config.positioning.layer {
-            crate::config::Layer::Background => Layer::Background,
-            crate::config::Layer::Bottom => Layer::Bottom,
-            crate::config::Layer::Top => Layer::Top,
-            crate::config::Layer::Overlay => Layer::Overlay,
-        };
-        window.set_layer(layer);
+        window.set_layer(Self::layer(config));
 
-        // Set anchors based on config
-        Self::apply_anchors(&window, &config.positioning.anchor);
+        // Set anchors based on config, unless a rule overrides it
+        let anchor = notification.anchor.unwrap_or(config.positioning.anchor);
+        Self::apply_anchors(&window, &anchor);
 
         // Apply margins
-        Self::apply_margins(&window, config, index);
+        Self::apply_margins(&window, config, anchor, index);
 
         // Enable keyboard focus on demand for inline reply and actions
         // OnDemand means keyboard is only active when window is clicked
@@ -63,30 +77,131 @@ impl NotificationWindow {
         // Set width
         window.set_default_width(config.appearance.width as i32);
 
-        // Create notification widget
-        let widget = NotificationWidget::new(notification, config, action_sender.clone());
-        window.set_child(Some(widget.widget()));
+        // Latest notification on top, older ones in a collapsed list below
+        let container = GtkBox::new(Orientation::Vertical, 4);
+        container.add_css_class("notification-stack");
+
+        let latest = GtkBox::new(Orientation::Vertical, 0);
+        container.append(&latest);
+
+        let toggle = Button::new();
+        toggle.add_css_class("stack-toggle");
+        toggle.set_visible(false);
+        container.append(&toggle);
+
+        let others = GtkBox::new(Orientation::Vertical, 4);
+        let revealer = Revealer::new();
+        revealer.set_transition_type(RevealerTransitionType::SlideDown);
+        revealer.set_child(Some(&others));
+        container.append(&revealer);
+
+        let hidden_count = Rc::new(Cell::new(0));
+        let revealer_toggle = revealer.clone();
+        let hidden_toggle = hidden_count.clone();
+        toggle.connect_clicked(move |button| {
+            let expanded = !revealer_toggle.reveals_child();
+            revealer_toggle.set_reveal_child(expanded);
+            Self::set_toggle_label(button, hidden_toggle.get(), expanded);
+        });
+
+        window.set_child(Some(&container));
 
         // Add window CSS class
         window.add_css_class("notification-window");
 
-        // Setup event handlers
-        let sender = action_sender.clone();
-        let id = notification.id;
-        let app_name = notification.app_name.clone();
-        Self::setup_event_handlers(&window, id, app_name.clone(), sender);
-
-        Self {
+        let mut stack = Self {
             window,
             notification_id: notification.id,
             app_name: notification.app_name.clone(),
-            widget,
+            group: notification.group_key().to_string(),
+            anchor,
+            members: Vec::new(),
+            latest,
+            others,
+            revealer,
+            toggle,
+            hidden_count,
             action_sender,
+        };
+        stack.push(notification, config);
+        stack
+    }
+
+    /// Put a notification on top of the stack
+    pub fn push(&mut self, notification: &Notification, config: &Config) {
+        let widget = NotificationWidget::new(notification, config, self.action_sender.clone());
+        Self::setup_event_handlers(
+            widget.widget(),
+            notification.id,
+            notification.app_name.clone(),
+            self.action_sender.clone(),
+        );
+
+        // The previous top moves to the front of the older ones
+        if let Some(previous) = self.members.last() {
+            self.latest.remove(previous.widget());
+            self.others.prepend(previous.widget());
+        }
+        self.latest.append(widget.widget());
+        self.members.push(widget);
+        self.update_toggle();
+    }
+
+    /// Take a notification out of the stack
+    ///
+    /// Returns `true` once the stack is empty and the window can be closed.
+    pub fn remove(&mut self, id: u32) -> bool {
+        let Some(pos) = self.members.iter().position(|widget| widget.id() == id) else {
+            return self.members.is_empty();
+        };
+        let widget = self.members.remove(pos);
+
+        if pos == self.members.len() {
+            // The top went away, the next newest takes its place
+            self.latest.remove(widget.widget());
+            if let Some(next) = self.members.last() {
+                self.others.remove(next.widget());
+                self.latest.append(next.widget());
+            }
+        } else {
+            self.others.remove(widget.widget());
+        }
+
+        self.update_toggle();
+        self.members.is_empty()
+    }
+
+    /// Show the "N more" button while older notifications are stacked
+    fn update_toggle(&self) {
+        let hidden = self.members.len().saturating_sub(1);
+        self.hidden_count.set(hidden);
+        if hidden == 0 {
+            self.revealer.set_reveal_child(false);
+        }
+        self.toggle.set_visible(hidden > 0);
+        Self::set_toggle_label(&self.toggle, hidden, self.revealer.reveals_child());
+    }
+
+    fn set_toggle_label(toggle: &Button, hidden: usize, expanded: bool) {
+        if expanded {
+            toggle.set_label("Show less");
+        } else {
+            toggle.set_label(&format!("{} more", hidden));
+        }
+    }
+
+    /// Layer-shell layer from the config
+    pub(super) fn layer(config: &Config) -> Layer {
+        match config.positioning.layer {
+            crate::config::Layer::Background => Layer::Background,
+            crate::config::Layer::Bottom => Layer::Bottom,
+            crate::config::Layer::Top => Layer::Top,
+            crate::config::Layer::Overlay => Layer::Overlay,
         }
     }
 
     /// Apply anchor positions based on config
-    fn apply_anchors(window: &Window, anchor: &Anchor) {
+    pub(super) fn apply_anchors(window: &Window, anchor: &Anchor) {
         // Reset all anchors
         window.set_anchor(Edge::Top, false);
         window.set_anchor(Edge::Bottom, false);
@@ -120,7 +235,7 @@ impl NotificationWindow {
     }
 
     /// Apply margins based on config and stacking index
-    fn apply_margins(window: &Window, config: &Config, index: usize) {
+    pub(super) fn apply_margins(window: &Window, config: &Config, anchor: Anchor, index: usize) {
         let margin = &config.positioning.margin;
         let gap = config.appearance.gap as i32;
         let estimated_height = 200; // Approximate notification height (with padding, icon, text, reply box, margins)
@@ -128,7 +243,7 @@ impl NotificationWindow {
         // Calculate stacking offset
         let stack_offset = (index as i32) * (estimated_height + gap);
 
-        match config.positioning.anchor {
+        match anchor {
             Anchor::TopLeft | Anchor::TopCenter | Anchor::TopRight => {
                 window.set_margin(Edge::Top, margin.top + stack_offset);
                 window.set_margin(Edge::Bottom, margin.bottom);
@@ -143,9 +258,9 @@ impl NotificationWindow {
         window.set_margin(Edge::Right, margin.right);
     }
 
-    /// Setup event handlers for the window
+    /// Setup event handlers for one notification in the window
     fn setup_event_handlers(
-        window: &Window,
+        target: &GtkBox,
         id: u32,
         app_name: String,
         sender: Sender<ActionEvent>,
@@ -192,7 +307,7 @@ impl NotificationWindow {
                 });
             }
         });
-        window.add_controller(click);
+        target.add_controller(click);
 
         // Middle click - dismiss
         let middle_click = gtk4::GestureClick::new();
@@ -205,7 +320,7 @@ impl NotificationWindow {
                 let _ = sender.send(ActionEvent::Dismissed { id }).await;
             });
         });
-        window.add_controller(middle_click);
+        target.add_controller(middle_click);
 
         // Right-click handler (context menu / dismiss)
         let right_click = gtk4::GestureClick::new();
@@ -218,7 +333,7 @@ impl NotificationWindow {
                 let _ = sender.send(ActionEvent::Dismissed { id }).await;
             });
         });
-        window.add_controller(right_click);
+        target.add_controller(right_click);
 
         // Swipe gesture for dismiss
         let swipe = gtk4::GestureDrag::new();
@@ -242,7 +357,7 @@ impl NotificationWindow {
             }
             gesture.reset();
         });
-        window.add_controller(swipe);
+        target.add_controller(swipe);
 
         // Hover handlers
         let motion = gtk4::EventControllerMotion::new();
@@ -265,7 +380,7 @@ impl NotificationWindow {
             });
         });
 
-        window.add_controller(motion);
+        target.add_controller(motion);
     }
 
     /// Show the window
@@ -283,26 +398,47 @@ impl NotificationWindow {
         debug!("Closed notification window for id={}", self.notification_id);
     }
 
-    /// Update the notification content
+    /// Update the content of a notification in the window
     pub fn update(&self, notification: &Notification, config: &Config) {
-        self.widget.update(notification, config);
+        if let Some(widget) = self
+            .members
+            .iter()
+            .find(|widget| widget.id() == notification.id)
+        {
+            widget.update(notification, config);
+        }
+    }
+
+    /// Check whether a notification is shown in this window
+    pub fn contains(&self, id: u32) -> bool {
+        self.members.iter().any(|widget| widget.id() == id)
     }
 
     /// Update the window position (for reordering)
     pub fn update_position(&self, config: &Config, index: usize) {
-        Self::apply_margins(&self.window, config, index);
+        Self::apply_margins(&self.window, config, self.anchor, index);
     }
 
-    /// Get the notification ID
+    /// Get the ID of the notification that opened the window
     pub fn id(&self) -> u32 {
         self.notification_id
     }
 
+    /// Get the key notifications are stacked by
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
     /// Get the app name
     pub fn app_name(&self) -> &str {
         &self.app_name
     }
 
+    /// Get the screen anchor this window is stacked on
+    pub fn anchor(&self) -> Anchor {
+        self.anchor
+    }
+
     /// Get the underlying GTK window
     pub fn window(&self) -> &Window {
         &self.window